@@ -0,0 +1,11 @@
+# Usage: add `tfm --init fish | source` to your `config.fish`,
+# then quitting tfm will change the shell's directory to the last directory of tfm.
+function tfm
+    set -l tmp (mktemp); or return
+    command tfm --last-dir-path="$tmp" $argv
+    set -l dir (cat -- $tmp)
+    rm -f -- $tmp
+    if test -d "$dir"; and test "$dir" != "$PWD"
+        cd -- $dir
+    end
+end
@@ -0,0 +1,12 @@
+# Usage: add `eval "$(tfm --init bash)"`(or zsh) to your shell rc file,
+# then quitting tfm will change the shell's directory to the last directory of tfm.
+tfm() {
+    local tmp dir
+    tmp="$(mktemp)" || return
+    command tfm --last-dir-path="$tmp" "$@"
+    dir="$(cat -- "$tmp")"
+    rm -f -- "$tmp"
+    if [ -d "$dir" ] && [ "$dir" != "$PWD" ]; then
+        cd -- "$dir" || return
+    fi
+}
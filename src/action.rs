@@ -1,13 +1,41 @@
 use std::env;
 
 use crate::app::App;
-use crate::config::{HIDDEN, OPENER, SORT_TYPE};
+use crate::config::{HIDDEN, SORT_TYPE};
+use crate::conflict::Conflict;
 use crate::dir::SortType;
+use crate::keymap;
+use crate::parser;
+use crate::settings::settings;
+
+// built-in `:` commands
+#[rustfmt::skip]
+pub const COMMANDS: &[&str] = &[
+    "set", "cd", "marks", "z", "zi", "q", "quit", "quit!", "rename", "cmd", "map", "unmap",
+    "mkdir", "touch", "ln", "trash-restore", "trash-empty", "jobs",
+];
+
+// NOTE: the operations of `CallAction`, keep it in sync with `CallAction::run`.
+#[rustfmt::skip]
+pub const CALL_OPS: &[&str] = &[
+    "quit", "updir", "open", "up", "down", "top", "bottom", "redraw", "command_mode", "shell",
+    "Shell", "search_next", "search_prev", "toggle", "toggle_all", "unselect", "cut", "copy",
+    "clear", "paste", "paste_ask", "paste_overwrite", "paste_skip", "paste_rename", "paste_newer",
+    "remove", "trash", "pause_job", "cancel_job", "undo", "redo", "jump_back", "jump_forward",
+    "mark_save", "mark_jump", "rename",
+];
 
 pub trait Action {
     fn run(&mut self, app: &mut App);
+
+    // NOTE: a bound action is cloned before running, so that it can change the keymap(like `:map`).
+    fn clone_box(&self) -> Box<dyn Action>;
+
+    // NOTE: `count` is the number typed before the keys(like `10j`), 0 means no count.
+    fn set_count(&mut self, _count: usize) {}
 }
 
+#[derive(Clone)]
 pub struct CallAction {
     op: String,
     count: usize,
@@ -23,21 +51,32 @@ impl CallAction {
 }
 
 impl Action for CallAction {
+    fn clone_box(&self) -> Box<dyn Action> {
+        Box::new(self.clone())
+    }
+
+    fn set_count(&mut self, count: usize) {
+        self.count = count;
+    }
+
     fn run(&mut self, app: &mut App) {
+        // NOTE: most operations treat no count(0) as 1
+        let count = std::cmp::max(self.count, 1);
         match self.op.as_str() {
-            "quit" => {
-                // quit tfm
-                if app.nav.mv_cp_total_size == 0 {
-                    app.quit = true;
-                } else {
-                    app.nav.error_message = "`paste` or `remove` operation in progress".to_string();
+            "quit" => app.quit(), // quit tfm, ask if `paste` or `remove` is in progress
+            "updir" => {
+                if app.nav.dirs.len() > 1 {
+                    app.nav.push_jump();
+                }
+                for _ in 0..count {
+                    app.nav.up_dir();
                 }
             }
-            "updir" => app.nav.up_dir(),
             "open" => {
                 let file_path = app.nav.cfile();
                 if let Some(file_path) = file_path {
                     if file_path.is_dir() {
+                        app.nav.push_jump();
                         if let Err(error) = env::set_current_dir(&file_path) {
                             app.nav.error_message = format!(
                                 "ERROR: open `{path}`:`{error}`",
@@ -47,14 +86,28 @@ impl Action for CallAction {
                         }
                         let dst_dir = app.nav.load_dir(&file_path, None);
                         app.nav.dirs.push(dst_dir);
+                        app.nav.record_visit(&file_path);
+                    } else if app.choose_files {
+                        app.chosen_files = if app.nav.selections.is_empty() {
+                            vec![file_path]
+                        } else {
+                            app.nav.selections.to_vec()
+                        };
+                        app.quit = true;
                     } else {
-                        let cmd = format!("{OPENER} '{}'", file_path.display());
+                        let cmd = format!(
+                            "{opener} '{path}'",
+                            opener = settings().opener,
+                            path = file_path.display()
+                        );
                         app.run_shell(&cmd, '$');
                     }
                 }
             }
-            "up" => app.nav.up(self.count),
-            "down" => app.nav.down(self.count),
+            "up" => app.nav.up(count),
+            "down" => app.nav.down(count),
+            // NOTE: `5gg` or `5G` jumps to the 5th file
+            "top" | "bottom" if self.count != 0 => app.nav.goto(self.count - 1),
             "top" => app.nav.top(),
             "bottom" => app.nav.bottom(),
             "redraw" => app.ui.renew(),
@@ -65,21 +118,57 @@ impl Action for CallAction {
                 app.run_shell(&shell, '$');
                 app.ui.renew();
             }
-            "search_next" => app.nav.search(false),
-            "search_prev" => app.nav.search(true),
-            "toggle" => app.nav.toggle(),
+            "search_next" => {
+                for _ in 0..count {
+                    app.nav.search(false);
+                }
+            }
+            "search_prev" => {
+                for _ in 0..count {
+                    app.nav.search(true);
+                }
+            }
+            "toggle" => {
+                for _ in 0..count {
+                    app.nav.toggle();
+                }
+            }
             "toggle_all" => app.nav.toggle_all(),
             "unselect" => app.nav.unselect(),
-            "cut" => app.nav.cut(),
-            "copy" => app.nav.copy(),
+            "cut" => app.nav.cut(count),
+            "copy" => app.nav.copy(count),
             "clear" => app.nav.clear(),
-            "paste" => app.nav.paste(),
-            "remove" => app.nav.remove(),
+            "paste" => app.nav.paste(settings().paste_conflict),
+            "paste_ask" | "paste_overwrite" | "paste_skip" | "paste_rename" | "paste_newer" => {
+                let policy = match self.op.as_str() {
+                    "paste_ask" => Conflict::Ask,
+                    "paste_overwrite" => Conflict::Overwrite,
+                    "paste_skip" => Conflict::Skip,
+                    "paste_rename" => Conflict::Rename,
+                    _ => Conflict::OverwriteIfNewer,
+                };
+                app.nav.paste(policy);
+            }
+            "remove" => app.remove(),
+            "trash" => app.nav.trash(),
+            "pause_job" => app.nav.pause_jobs(),
+            "cancel_job" => app.cancel_jobs(None),
+            "undo" | "redo" => {
+                for _ in 0..count {
+                    app.nav.undo(self.op == "redo");
+                }
+            }
+            "jump_back" | "jump_forward" => {
+                for _ in 0..count {
+                    app.nav.jump(self.op == "jump_forward");
+                }
+            }
+            "mark_save" | "mark_jump" => app.pending_char = Some(self.op.clone()),
             "rename" => {
                 if let Ok(ref cdir) = app.nav.cdir().lock() {
                     if let Some(files) = cdir.files() {
                         let file = &files[cdir.sp + cdir.bp];
-                        let s = format!("rename {}", file.file_name);
+                        let s = format!("rename {}", parser::quote(&file.file_name));
                         app.command_mode(Some(':'), Some(&s));
                     } else {
                         app.nav.error_message = "ERROR: No selected file to rename".to_string();
@@ -94,27 +183,44 @@ impl Action for CallAction {
     }
 }
 
+#[derive(Clone)]
 pub struct SetAction {
     op: String,
     val: String,
 }
 
 impl SetAction {
-    pub fn new(c: &str) -> Self {
-        let s: Vec<_> = c.split(' ').collect();
-        let val = if s.len() > 1 {
-            s[1].to_string()
-        } else {
-            String::new()
-        };
-        Self {
-            op: s[0].to_string(),
-            val,
+    // NOTE: `c` is like `sortby size`, it's parsed the same as the command line.
+    pub fn new(c: &str) -> Result<Self, String> {
+        match &parser::parse(c)?[..] {
+            [words] => Self::from_words(words),
+            _ => Err(format!("expected `option [value]`, found `{c}`")),
+        }
+    }
+
+    pub fn from_words(words: &[String]) -> Result<Self, String> {
+        match words {
+            [op] => Ok(Self {
+                op: op.to_string(),
+                val: String::new(),
+            }),
+            [op, val] => Ok(Self {
+                op: op.to_string(),
+                val: val.to_string(),
+            }),
+            _ => Err(format!(
+                "expected `option [value]`, found `{words}`",
+                words = words.join(" ")
+            )),
         }
     }
 }
 
 impl Action for SetAction {
+    fn clone_box(&self) -> Box<dyn Action> {
+        Box::new(self.clone())
+    }
+
     fn run(&mut self, app: &mut App) {
         match self.op.as_str() {
             "hidden" | "unhidden" | "hidden!" => {
@@ -136,12 +242,7 @@ impl Action for SetAction {
             "sortby" => {
                 match SORT_TYPE.write() {
                     Ok(mut sort_type) => {
-                        *sort_type = match self.val.as_str() {
-                            "natural" => SortType::Natural,
-                            "mtime" => SortType::ModifyTime,
-                            "size" => SortType::Size,
-                            _ => SortType::Natural,
-                        }
+                        *sort_type = SortType::from_name(&self.val).unwrap_or(SortType::Natural)
                     }
                     Err(err) => {
                         app.nav.error_message =
@@ -160,6 +261,7 @@ impl Action for SetAction {
     }
 }
 
+#[derive(Clone)]
 pub struct CmdAction {
     prefix: char,
     cmd: String,
@@ -174,35 +276,128 @@ impl CmdAction {
     }
 }
 
+// `:cmd <name> <body>` or `:map <keys> <command>`
+fn define(app: &mut App, op: &str, name: &str, body: &str) {
+    match op {
+        _ if name.is_empty() || body.is_empty() => {
+            app.nav.error_message = match op {
+                "cmd" => "`cmd` expects a name and a body".to_string(),
+                _ => "`map` expects keys and a command".to_string(),
+            }
+        }
+        "cmd" => {
+            if let Err(err) = keymap::define_cmd(&mut app.keymap.commands, name, body) {
+                app.nav.error_message = format!("`cmd`: {err}");
+            }
+        }
+        _ => app.map(name, body),
+    }
+}
+
+impl CmdAction {
+    // NOTE: `words` is a command parsed by `parser::parse`, it's never empty.
+    fn run_cmd(&self, app: &mut App, words: &[String]) {
+        let (op, args) = (words[0].as_str(), &words[1..]);
+        match op {
+            "set" => match SetAction::from_words(args) {
+                Ok(mut a) => a.run(app),
+                Err(err) => app.nav.error_message = format!("`set`: {err}"),
+            },
+            "cd" => match args {
+                [] => app.nav.cd("~"),
+                [dir] => app.nav.cd(dir),
+                _ => app.nav.error_message = "`cd` expects one directory".to_string(),
+            },
+            "marks" => app.nav.show_marks(),
+            "z" => app.nav.z(args, args.is_empty()),
+            "zi" => app.nav.z(args, true),
+            "q" => app.normal_mode(), // quit command mode
+            "quit" => CallAction::new("quit", 0).run(app), // quit tfm
+            "quit!" => app.quit = true, // NOTE: quit tfm always
+            "rename" => match args {
+                [new_name] => app.nav.rename(new_name),
+                _ => app.nav.error_message = "`rename` expects one new name".to_string(),
+            },
+            "mkdir" | "touch" if args.is_empty() => {
+                app.nav.error_message = format!("`{op}` expects paths");
+            }
+            "mkdir" => args.iter().for_each(|p| app.nav.mkdir(p)),
+            "touch" => args.iter().for_each(|p| app.nav.touch(p)),
+            "ln" => match args {
+                [s, target] if s == "-s" => app.nav.link(target, None, true),
+                [s, target, link] if s == "-s" => app.nav.link(target, Some(link), true),
+                [target] if target != "-s" => app.nav.link(target, None, false),
+                [target, link] => app.nav.link(target, Some(link), false),
+                _ => app.nav.error_message = "`ln` expects `[-s] <target> [<link>]`".to_string(),
+            },
+            "trash-restore" => app.nav.show_trash(),
+            "trash-empty" => app.trash_empty(),
+            "jobs" => match args {
+                [] => app.nav.show_jobs(),
+                [id] => match id.trim_start_matches('#').parse() {
+                    Ok(id) => app.nav.show_job(id),
+                    Err(_) => app.nav.error_message = format!("`jobs`: invalid job id `{id}`"),
+                },
+                _ => app.nav.error_message = "`jobs` expects at most one job id".to_string(),
+            },
+            // NOTE: only after `;`, otherwise `run` takes the rest of the line as the body.
+            "cmd" | "map" => {
+                let (name, body) = match args {
+                    [] => ("", String::new()),
+                    [name, body] => (name.as_str(), body.clone()),
+                    [name, body @ ..] => (
+                        name.as_str(),
+                        body.iter()
+                            .map(|w| parser::quote(w))
+                            .collect::<Vec<_>>()
+                            .join(" "),
+                    ),
+                };
+                define(app, op, name, &body);
+            }
+            "unmap" => match args {
+                [key] => app.unmap(key),
+                _ => app.nav.error_message = "`unmap` expects keys".to_string(),
+            },
+            _ if app.keymap.commands.contains_key(op) => app.run_user_cmd(op, args),
+            _ => {
+                app.nav.error_message = format!(
+                    "CmdAction run `{prefix}{cmd}` failed:`No Operation`",
+                    prefix = self.prefix,
+                    cmd = words.join(" ")
+                );
+            }
+        }
+    }
+}
+
 impl Action for CmdAction {
+    fn clone_box(&self) -> Box<dyn Action> {
+        Box::new(self.clone())
+    }
+
     fn run(&mut self, app: &mut App) {
+        let (op, rest) = keymap::split_word(self.cmd.trim());
+        if self.prefix == ':' && matches!(op, "cmd" | "map") {
+            let (name, body) = keymap::split_word(rest);
+            match parser::definition_body(body) {
+                Ok(body) => define(app, op, name, &body),
+                Err(err) => app.nav.error_message = format!("`:{cmd}`: {err}", cmd = &self.cmd),
+            }
+            return;
+        }
         match self.prefix {
-            ':' => {
-                let (op, cmd) = match self.cmd.find(' ') {
-                    Some(i) => (&self.cmd[0..i], &self.cmd[i + 1..]),
-                    None => (&self.cmd[..], ""),
-                };
-                match op {
-                    "set" => {
-                        SetAction::new(cmd).run(app);
-                    }
-                    "cd" => app.nav.cd(cmd),
-                    "q" => app.normal_mode(), // quit command mode
-                    "quit" => CallAction::new("quit", 0).run(app), // quit tfm
-                    "quit!" => app.quit = true, // NOTE: quit tfm always
-                    "rename" => {
-                        let new_name = cmd.trim();
-                        app.nav.rename(new_name);
-                    }
-                    _ => {
-                        app.nav.error_message = format!(
-                            "CmdAction run `{prefix}{cmd}` failed:`No Operation`",
-                            prefix = self.prefix,
-                            cmd = &self.cmd
-                        );
+            ':' => match parser::parse(&self.cmd) {
+                Ok(cmds) => {
+                    // NOTE: `:set hidden!; cd ~/src` runs commands one by one
+                    for words in cmds {
+                        self.run_cmd(app, &words);
                     }
                 }
-            }
+                Err(err) => {
+                    app.nav.error_message = format!("`:{cmd}`: {err}", cmd = &self.cmd);
+                }
+            },
             '/' | '?' => app.nav.search(false),
             '$' | '!' | '&' => app.run_shell(&self.cmd, self.prefix),
             _ => {
@@ -1,60 +1,112 @@
 use crossbeam_channel::select;
-use crossterm::event::{self, Event, KeyEvent, KeyModifiers};
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyModifiers};
 use std::{
-    collections::HashMap,
-    path::Path,
+    collections::VecDeque,
+    path::{Path, PathBuf},
     process::{Command, Stdio},
     thread, time,
 };
 
-use crate::action::{Action, CallAction, CmdAction, SetAction};
-use crate::config::actions::KEYS;
+use crate::action::{Action, CmdAction};
+use crate::clipboard;
+use crate::job;
+use crate::journal;
+use crate::keymap::{self, Keymap};
+use crate::menu::MenuKind;
 use crate::nav::Nav;
+use crate::parser;
+use crate::remote;
+use crate::trash;
 use crate::ui::Ui;
 use crate::utils;
 
+const MAX_CMD_DEPTH: usize = 16;
+
+// Answer of a prompt, `a` means yes to this and all the following questions.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Answer {
+    Yes,
+    No,
+    All,
+    Cancel, // NOTE: `Esc`, cancel the whole operation
+}
+
+type PromptCallback = Box<dyn FnOnce(&mut App, Answer)>;
+
+// A question waiting for `y`, `n` or `a` in the status line.
+pub struct Prompt {
+    pub msg: String,
+    id: usize,
+    callback: PromptCallback,
+}
+
 pub struct App {
     pub ui: Ui,
     pub nav: Nav,
     pub quit: bool,
+    quit_after_jobs: bool, // NOTE: the unfinished jobs are being canceled to quit
     keys: String,
+    pub keymap: Keymap,
+    cmd_depth: usize, // NOTE: nesting depth of user-defined commands, to stop endless recursion
+    pub pending_char: Option<String>, // NOTE: the CallAction waiting for a char, like `m` of `m<char>`
+    pub prompts: VecDeque<Prompt>,    // NOTE: parallel jobs may ask at the same time
+    next_prompt_id: usize,
+    pub prompt_chan: utils::PromptChan, // NOTE: messages completed by threads
+
+    // NOTE: file picker mode(`--choosefiles`), opening a file chooses files instead of running `opener`
+    pub choose_files: bool,
+    pub chosen_files: Vec<PathBuf>,
 
     pub ev_chan: utils::CrossTermEventChan,
+    pub remote_chan: utils::StringChan,
 }
 
 impl App {
-    pub fn new() -> Self {
+    pub fn new(keymap: Keymap) -> Self {
         Self {
             ui: Ui::new(),
             nav: Nav::new(),
             quit: false,
+            quit_after_jobs: false,
             keys: String::new(),
+            keymap,
+            cmd_depth: 0,
+            pending_char: None,
+            prompts: VecDeque::new(),
+            next_prompt_id: 0,
+            prompt_chan: utils::PromptChan::new(),
+
+            choose_files: false,
+            chosen_files: Vec::new(),
 
             ev_chan: utils::CrossTermEventChan::new(),
+            remote_chan: utils::StringChan::new(),
         }
     }
 
     pub fn run(&mut self, p: &Path) -> std::io::Result<()> {
-        let mut keys: HashMap<&'static str, Box<dyn Action>> = HashMap::new();
-        for t in KEYS {
-            if t.0 == "c" {
-                keys.insert(t.1, Box::new(CallAction::new(t.2, 1)));
-            } else if t.0 == "s" {
-                keys.insert(t.1, Box::new(SetAction::new(t.2)));
-            } else if t.0 == "m" {
-                let prefix = t.2.chars().next().unwrap();
-                keys.insert(t.1, Box::new(CmdAction::new(&t.2[1..], prefix)));
+        // NOTE: remote control is optional, tfm still works without it.
+        let _remote = match remote::Server::start(self.remote_chan.string_send.clone()) {
+            Ok(server) => {
+                std::env::set_var("rust_tfm_id", &server.id);
+                Some(server)
             }
-        }
+            Err(err) => {
+                log::error!("{err} => start remote server failed");
+                None
+            }
+        };
 
         self.ui.init()?;
         self.nav.load_dirs(p)?;
         self.nav.update_preview(false, &self.ui.preview_layout);
         self.ev_chan_thread();
+        clipboard::watch(self.nav.clipboard_chan.unit_send.clone());
 
         // Infinite loop
         while !self.quit {
-            self.ui.draw(&self.nav)?;
+            self.ui
+                .draw(&self.nav, self.prompts.front().map(|p| p.msg.as_str()))?;
             select! {
                 recv(self.nav.dir_chan.unit_recv) -> _ => {
                     log::info!("`dir_chan` received!!!");
@@ -62,25 +114,72 @@ impl App {
                 recv(self.nav.reg_chan.unit_recv) -> _ => {
                     log::info!("`reg_chan` received!!!");
                 }
+                recv(self.nav.clipboard_chan.unit_recv) -> _ => {
+                    log::info!("`clipboard_chan` received!!!");
+                    self.nav.load_clipboard();
+                }
                 recv(self.nav.err_msg_chan.string_recv) -> err => {
                     self.nav.error_message = err.unwrap();
                 }
-                recv(self.nav.mv_cp_total_chan.u64_recv) -> n => {
-                    self.nav.mv_cp_total_size = n.unwrap();
-                    self.nav.mv_cp_size = 0;
-                    log::info!("mv_cp_total_chan received => total_size: {}, mv_cp_size: {}", self.nav.mv_cp_total_size, self.nav.mv_cp_size);
+                recv(self.nav.jobs.job_chan.job_recv) -> event => {
+                    let (id, event) = event.unwrap();
+                    log::info!("job_chan received => job: {id}, event: {event:?}");
+                    if let job::Event::Error(ref err) = event {
+                        self.nav.error_message = err.clone();
+                    }
+                    let cut_sources = match event {
+                        job::Event::Finished { .. } => self
+                            .nav
+                            .jobs
+                            .get(id)
+                            .filter(|j| j.kind == job::Kind::Move)
+                            .map(|j| j.sources.clone()),
+                        _ => None,
+                    };
+                    if let Some((ops, msg)) = self.nav.jobs.handle(id, event) {
+                        self.nav.journal.record(ops);
+                        if let Some(sources) = cut_sources {
+                            self.nav.keep_unpasted(&sources);
+                        }
+                        if let Some(msg) = msg {
+                            self.nav.error_message = msg;
+                        }
+                    }
+                    self.nav.refresh_jobs_menu();
+                    if self.quit_after_jobs && self.nav.jobs.pending().next().is_none() {
+                        self.quit = true;
+                    }
                     self.nav.check_dirs();  // NOTE: this function doesn't run asynchronously.
                     self.nav.update_preview(true, &self.ui.preview_layout);
                 }
-                recv(self.nav.mv_cp_chan.u64_recv) -> n => {
-                    self.nav.mv_cp_size += n.unwrap();
+                recv(self.nav.ask_chan.ask_recv) -> question => {
+                    // NOTE: a `paste` thread waits for the answer
+                    let (msg, answer_send) = question.unwrap();
+                    if self.quit_after_jobs {
+                        let _ = answer_send.send(Answer::Cancel);
+                        continue;
+                    }
+                    self.ask(msg, move |_, answer| {
+                        if let Err(err) = answer_send.send(answer) {
+                            log::error!("{err} => answer_send send failed");
+                        }
+                    });
+                }
+                recv(self.prompt_chan.prompt_recv) -> update => {
+                    let (id, msg) = update.unwrap();
+                    if let Some(prompt) = self.prompts.iter_mut().find(|p| p.id == id) {
+                        prompt.msg = msg;
+                    }
+                }
+                recv(self.remote_chan.string_recv) -> cmd => {
+                    self.run_remote(&cmd.unwrap());
                     self.nav.check_dirs();  // NOTE: this function doesn't run asynchronously.
                     self.nav.update_preview(true, &self.ui.preview_layout);
                 }
                 recv(self.ev_chan.ev_recv) -> ev => {
                     log::info!("`ev_chan` received!!!");
                     let ev = ev.unwrap();
-                    self.handle_event(ev, &mut keys)?;
+                    self.handle_event(ev)?;
                     // NOTE: When Operating System send keys(such as Paste text from clipboard) to tfm,
                     // the time slot between keys are very short.
                     // So the `loop` ensure tfm will receive all keys then redraw window.
@@ -88,7 +187,7 @@ impl App {
                         select! {
                             recv(self.ev_chan.ev_recv) -> ev => {
                                 let ev = ev.unwrap();
-                                if !self.handle_event(ev, &mut keys)? {
+                                if !self.handle_event(ev)? {
                                     break;
                                 }
                             }
@@ -103,15 +202,56 @@ impl App {
         Ok(())
     }
 
-    fn handle_event(
-        &mut self,
-        ev: Event,
-        keys: &mut HashMap<&'static str, Box<dyn Action>>,
-    ) -> std::io::Result<bool> {
+    // NOTE: a remote command without prefix is treated as a tfm command(`:`)
+    fn run_remote(&mut self, cmd: &str) {
+        match keymap::cmd_action(cmd) {
+            Ok(mut a) => a.run(self),
+            Err(err) => self.nav.error_message = format!("remote: {err}"),
+        }
+    }
+
+    // `:map <keys> <command>`, `command` without prefix is a tfm command(`:`)
+    pub fn map(&mut self, keys: &str, cmd: &str) {
+        match keymap::cmd_action(cmd) {
+            Ok(a) => {
+                self.keymap.keys.insert(keymap::parse_key(keys), a);
+            }
+            Err(err) => self.nav.error_message = format!("`map`: {err}"),
+        }
+    }
+
+    pub fn unmap(&mut self, keys: &str) {
+        if self.keymap.keys.remove(&keymap::parse_key(keys)).is_none() {
+            self.nav.error_message = format!("`unmap`: `{keys}` is not mapped");
+        }
+    }
+
+    // NOTE: `args` are quoted and appended to the body of the command.
+    pub fn run_user_cmd(&mut self, name: &str, args: &[String]) {
+        if self.cmd_depth >= MAX_CMD_DEPTH {
+            self.nav.error_message = format!("`{name}`: too many nested commands");
+            return;
+        }
+        let mut cmd = self.keymap.commands[name].clone();
+        for arg in args {
+            cmd += " ";
+            cmd += &parser::quote(arg);
+        }
+        match keymap::cmd_action(&cmd) {
+            Ok(mut a) => {
+                self.cmd_depth += 1;
+                a.run(self);
+                self.cmd_depth -= 1;
+            }
+            Err(err) => self.nav.error_message = format!("`{name}`: {err}"),
+        }
+    }
+
+    fn handle_event(&mut self, ev: Event) -> std::io::Result<bool> {
         match ev {
             Event::Key(ev) => {
                 self.nav.error_message.clear();
-                self.handle_key(&ev, keys);
+                self.handle_key(&ev);
             }
             Event::Resize(cols, rows) => self.resize(cols, rows)?,
             _ => return Ok(false),
@@ -152,19 +292,36 @@ impl App {
         });
     }
 
-    fn handle_key(&mut self, ev: &KeyEvent, keys: &mut HashMap<&'static str, Box<dyn Action>>) {
-        if self.nav.cmd_prefix == char::default() {
-            self.handle_normal_key(ev, keys);
+    fn handle_key(&mut self, ev: &KeyEvent) {
+        if !self.prompts.is_empty() {
+            self.handle_prompt_key(ev);
+        } else if self.nav.menu.is_some() {
+            self.handle_menu_key(ev);
+        } else if let Some(op) = self.pending_char.take() {
+            self.handle_pending_char(ev, &op);
+        } else if self.nav.cmd_prefix == char::default() {
+            self.handle_normal_key(ev);
         } else {
             self.handle_cmd_key(ev);
         }
     }
 
-    fn handle_normal_key(
-        &mut self,
-        ev: &KeyEvent,
-        keys: &mut HashMap<&'static str, Box<dyn Action>>,
-    ) {
+    fn handle_normal_key(&mut self, ev: &KeyEvent) {
+        // NOTE: count prefix, like `10j`, `3dd` or `5G`. `0` can't start a count.
+        if self.keys.is_empty()
+            && !ev
+                .modifiers
+                .intersects(KeyModifiers::CONTROL | KeyModifiers::ALT)
+        {
+            if let KeyCode::Char(ch @ '0'..='9') = ev.code {
+                if ch != '0' || self.nav.count != 0 {
+                    let digit = ch.to_digit(10).unwrap() as usize;
+                    self.nav.count = self.nav.count.saturating_mul(10).saturating_add(digit);
+                    return;
+                }
+            }
+        }
+
         // NOTE: not supported C-A-j
         let key = utils::keycode2str(ev.code);
         if ev.modifiers.contains(KeyModifiers::CONTROL) {
@@ -188,12 +345,15 @@ impl App {
         log::info!("{keys}:{key}", keys = self.keys);
         if key == "Esc" {
             self.keys.clear();
-        } else if let Some(a) = keys.get_mut(self.keys.as_str()) {
+            self.nav.count = 0;
+        } else if let Some(a) = self.keymap.keys.get(self.keys.as_str()) {
+            let mut a = a.clone_box();
+            a.set_count(std::mem::take(&mut self.nav.count));
             a.run(self);
             self.keys.clear();
         } else {
             let mut have = false;
-            for k in keys.keys() {
+            for k in self.keymap.keys.keys() {
                 if k.starts_with(&self.keys) {
                     have = true;
                     break;
@@ -202,14 +362,351 @@ impl App {
             if !have {
                 self.nav.error_message = format!("Unknown mapping: {key}", key = self.keys);
                 self.keys.clear();
+                self.nav.count = 0;
+            }
+        }
+    }
+
+    // NOTE: other keys are ignored until the prompt is answered.
+    fn handle_prompt_key(&mut self, ev: &KeyEvent) {
+        let key = utils::keycode2str(ev.code);
+        let control = ev.modifiers.contains(KeyModifiers::CONTROL);
+        let answer = match key.as_str() {
+            "c" if control => Answer::Cancel,
+            "y" | "Y" => Answer::Yes,
+            "n" | "N" => Answer::No,
+            "a" | "A" => Answer::All,
+            "Esc" | "q" => Answer::Cancel,
+            _ => return,
+        };
+        if let Some(prompt) = self.prompts.pop_front() {
+            (prompt.callback)(self, answer);
+        }
+    }
+
+    // Ask a question in the status line, `callback` is called with the answer. Questions are
+    // asked one by one, the id of the prompt is returned.
+    pub fn ask<F: FnOnce(&mut App, Answer) + 'static>(
+        &mut self,
+        msg: String,
+        callback: F,
+    ) -> usize {
+        let id = self.next_prompt_id;
+        self.next_prompt_id += 1;
+        self.prompts.push_back(Prompt {
+            msg,
+            id,
+            callback: Box::new(callback),
+        });
+        id
+    }
+
+    // Like `ask`, `msg` is called with the number of files under `paths` and their total size.
+    // NOTE: counting walks the whole trees, so it's done in a thread. The prompt is shown at once
+    // and its message is completed when the count arrives.
+    pub fn ask_summary<M, F>(&mut self, paths: Vec<PathBuf>, msg: M, callback: F)
+    where
+        M: Fn(&str) -> String + Send + 'static,
+        F: FnOnce(&mut App, Answer) + 'static,
+    {
+        self.ask_summary_with((0, 0), paths, msg, callback);
+    }
+
+    // NOTE: like `ask_summary`, `base` files and bytes are already counted, so nothing is left
+    // to count if `paths` is empty.
+    fn ask_summary_with<M, F>(&mut self, base: (usize, u64), paths: Vec<PathBuf>, msg: M, cb: F)
+    where
+        M: Fn(&str) -> String + Send + 'static,
+        F: FnOnce(&mut App, Answer) + 'static,
+    {
+        let summary = move |(count, size): (usize, u64)| {
+            let (count, size) = (base.0 + count, base.1 + size);
+            format!("{count} files, {size}", size = utils::human_size(size))
+        };
+        if paths.is_empty() {
+            self.ask(msg(&summary((0, 0))), cb);
+            return;
+        }
+        let id = self.ask(msg("counting files..."), cb);
+        let prompt_send = self.prompt_chan.prompt_send.clone();
+        thread::spawn(move || {
+            let summary = summary(utils::files_summary(&paths));
+            if let Err(err) = prompt_send.send((id, msg(&summary))) {
+                log::error!("{err} => prompt_send send failed");
+            }
+        });
+    }
+
+    pub fn quit(&mut self) {
+        let pending = self.nav.jobs.pending().count();
+        if pending == 0 {
+            self.quit = true;
+            return;
+        }
+        let msg = move |summary: &str| {
+            format!(
+                "{pending} `paste` or `remove` jobs unfinished({summary} left), cancel them and quit? [y/n]"
+            )
+        };
+        let (left, uncounted) = self.nav.jobs.remaining();
+        self.ask_summary_with(left, uncounted, msg, |app, answer| {
+            if matches!(answer, Answer::Yes | Answer::All) {
+                app.cancel_and_quit();
+            }
+        });
+    }
+
+    // NOTE: the file being copied or removed is finished first, tfm quits when all the jobs
+    // have reported `Finished`. Questions of `paste` threads are answered with `Esc`.
+    fn cancel_and_quit(&mut self) {
+        self.quit_after_jobs = true;
+        while let Some(prompt) = self.prompts.pop_front() {
+            (prompt.callback)(self, Answer::Cancel);
+        }
+        let ids: Vec<_> = self.nav.jobs.pending().map(|j| j.id).collect();
+        for id in &ids {
+            self.nav.cancel_job(*id);
+        }
+        match ids.len() {
+            0 => self.quit = true,
+            n => self.nav.error_message = format!("Canceling {n} jobs before quitting..."),
+        }
+    }
+
+    // NOTE: None cancels all the unfinished jobs
+    pub fn cancel_jobs(&mut self, id: Option<usize>) {
+        let ids: Vec<_> = self
+            .nav
+            .jobs
+            .pending()
+            .filter(|j| id.is_none_or(|id| j.id == id))
+            .map(|j| j.id)
+            .collect();
+        let msg = match (id, ids.len()) {
+            (_, 0) => {
+                self.nav.error_message = "No `paste` or `remove` to cancel".to_string();
+                return;
+            }
+            (Some(id), _) => format!(
+                "Cancel job {summary}? [y/n]",
+                summary = self.nav.jobs.get(id).unwrap().summary()
+            ),
+            (None, n) => format!("Cancel {n} `paste` or `remove` jobs? [y/n]"),
+        };
+        self.ask(msg, move |app, answer| {
+            if matches!(answer, Answer::Yes | Answer::All) {
+                for id in ids {
+                    app.nav.cancel_job(id);
+                }
+            }
+        });
+    }
+
+    pub fn remove(&mut self) {
+        if self.nav.selections.is_empty() {
+            self.nav.error_message = "No selected file to remove".to_string();
+            return;
+        }
+        let paths = self.nav.selections.to_vec();
+        let n = paths.len();
+        let msg = move |summary: &str| format!("Remove {n} selected({summary})? [y/n]");
+        self.ask_summary(paths, msg, |app, answer| {
+            if matches!(answer, Answer::Yes | Answer::All) {
+                app.nav.remove();
+            }
+        });
+    }
+
+    // NOTE: any key other than a char(like `Esc`) cancels the pending CallAction.
+    fn handle_pending_char(&mut self, ev: &KeyEvent, op: &str) {
+        let ch = match ev.code {
+            KeyCode::Char(ch)
+                if !ev
+                    .modifiers
+                    .intersects(KeyModifiers::CONTROL | KeyModifiers::ALT) =>
+            {
+                ch
             }
+            _ => return,
+        };
+        match op {
+            "mark_save" => self.nav.save_mark(ch),
+            "mark_jump" => self.nav.jump_mark(ch),
+            _ => log::error!("`{op}` doesn't wait for a char"),
+        }
+    }
+
+    fn handle_menu_key(&mut self, ev: &KeyEvent) {
+        let key = utils::keycode2str(ev.code);
+        let control = ev.modifiers.contains(KeyModifiers::CONTROL);
+        let menu = self.nav.menu.as_mut().unwrap();
+        let rows = self.nav.useful_rows as usize;
+        match key.as_str() {
+            "h" | "Left" if matches!(menu.kind, MenuKind::Job(_)) => self.nav.show_jobs(),
+            "Esc" | "q" | "h" | "Left" => self.nav.menu = None,
+            "c" if control => self.nav.menu = None,
+            "j" | "Down" => menu.down(1),
+            "k" | "Up" => menu.up(1),
+            "d" if control => menu.down(rows / 2),
+            "u" if control => menu.up(rows / 2),
+            "g" | "Home" => menu.top(),
+            "G" | "End" => menu.bottom(),
+            "Enter" | "l" | "Right" => {
+                let kind = menu.kind;
+                let value = menu.value().map(String::from);
+                self.nav.menu = None;
+                if let Some(value) = value {
+                    self.pick_menu_entry(kind, &value);
+                }
+            }
+            "p" if matches!(menu.kind, MenuKind::Jobs | MenuKind::Job(_)) => {
+                if let Some(id) = self.menu_job_id() {
+                    if let Err(err) = self.nav.jobs.pause(id) {
+                        self.nav.error_message = err;
+                    }
+                    self.nav.refresh_jobs_menu();
+                }
+            }
+            "d" | "Delete" if matches!(menu.kind, MenuKind::Jobs | MenuKind::Job(_)) => {
+                if let Some(id) = self.menu_job_id() {
+                    self.cancel_jobs(Some(id));
+                }
+            }
+            "d" | "Delete" => {
+                let kind = menu.kind;
+                if let Some(value) = menu.value().map(String::from) {
+                    menu.remove();
+                    self.delete_menu_entry(kind, &value);
+                }
+            }
+            _ => {}
         }
     }
 
+    fn pick_menu_entry(&mut self, kind: MenuKind, value: &str) {
+        match kind {
+            MenuKind::Marks => {
+                if let Some(ch) = value.chars().next() {
+                    self.nav.jump_mark(ch);
+                }
+            }
+            MenuKind::Frecency => self.nav.cd_sel(Path::new(value), None),
+            MenuKind::Trash => self.nav.restore_trash(Path::new(value)),
+            MenuKind::Jobs => {
+                if let Ok(id) = value.parse() {
+                    self.nav.show_job(id);
+                }
+            }
+            // NOTE: go to the destination directory or to the source file
+            MenuKind::Job(id) => {
+                let p = Path::new(value);
+                let dst = self.nav.jobs.get(id).and_then(|j| j.dst.clone());
+                match (p.parent(), p.file_name()) {
+                    _ if value.is_empty() => self.nav.show_job(id),
+                    _ if dst.as_deref() == Some(p) => self.nav.cd_sel(p, None),
+                    (Some(dir), Some(name)) => self.nav.cd_sel(dir, name.to_str()),
+                    _ => self.nav.show_job(id),
+                }
+            }
+        }
+    }
+
+    // NOTE: the job under the cursor of `:jobs` or the job of the detail view
+    fn menu_job_id(&self) -> Option<usize> {
+        let menu = self.nav.menu.as_ref()?;
+        match menu.kind {
+            MenuKind::Jobs => menu.value()?.parse().ok(),
+            MenuKind::Job(id) => Some(id),
+            _ => None,
+        }
+    }
+
+    fn delete_menu_entry(&mut self, kind: MenuKind, value: &str) {
+        match kind {
+            MenuKind::Marks => {
+                if let Some(ch) = value.chars().next() {
+                    self.nav.delete_mark(ch);
+                }
+            }
+            MenuKind::Frecency => self.nav.forget_dir(Path::new(value)),
+            MenuKind::Trash => self.ask_delete_trash(Path::new(value)),
+            MenuKind::Jobs | MenuKind::Job(_) => {} // NOTE: `d` cancels the job
+        }
+    }
+
+    // NOTE: the entry is put back to the menu if the answer isn't yes
+    fn ask_delete_trash(&mut self, info: &Path) {
+        let entry = match self.nav.find_trash(info) {
+            Some(entry) => entry,
+            None => return,
+        };
+        let path = entry.path.display().to_string();
+        let msg = move |summary: &str| format!("Delete `{path}`({summary}) permanently? [y/n]");
+        self.ask_summary(vec![entry.file.clone()], msg, move |app, answer| {
+            if matches!(answer, Answer::Yes | Answer::All) {
+                app.nav.delete_trash(&[entry]);
+            } else {
+                let ind = app.nav.menu.as_ref().map_or(0, |m| m.ind);
+                app.nav.show_trash();
+                if let Some(ref mut menu) = app.nav.menu {
+                    menu.ind = ind;
+                }
+            }
+        });
+    }
+
+    // `:trash-empty`
+    pub fn trash_empty(&mut self) {
+        let entries = trash::list();
+        if entries.is_empty() {
+            self.nav.error_message = "Trash is empty".to_string();
+            return;
+        }
+        let files: Vec<_> = entries.iter().map(|e| e.file.clone()).collect();
+        let n = entries.len();
+        let msg = move |summary: &str| format!("Empty the trash({n} trashed, {summary})? [y/n]");
+        self.ask_summary(files, msg, move |app, answer| {
+            if matches!(answer, Answer::Yes | Answer::All) {
+                app.nav.delete_trash(&entries);
+            }
+        });
+    }
+
+    // NOTE: return false if `ev` isn't handled, the shown command is accepted and `ev` is handled
+    // as usual(like `Enter` runs it).
+    fn handle_history_search_key(&mut self, ev: &KeyEvent) -> bool {
+        let control = ev.modifiers.contains(KeyModifiers::CONTROL);
+        let alt = ev.modifiers.contains(KeyModifiers::ALT);
+        let query = self.nav.history_search.as_mut().unwrap();
+        match ev.code {
+            KeyCode::Char('r') if control => self.nav.history_search(true),
+            KeyCode::Char('g' | 'c') if control => self.nav.cancel_history_search(),
+            KeyCode::Esc => self.nav.cancel_history_search(),
+            KeyCode::Backspace => {
+                query.pop();
+            }
+            KeyCode::Char(ch) if !control && !alt => {
+                query.push(ch);
+                self.nav.history_search(false);
+            }
+            _ => {
+                self.nav.history_search = None;
+                return false;
+            }
+        }
+        true
+    }
+
     fn handle_cmd_key(&mut self, ev: &KeyEvent) {
+        if self.nav.history_search.is_some() && self.handle_history_search_key(ev) {
+            return;
+        }
         let key = utils::keycode2str(ev.code);
         log::info!("{key}:{cmd}", cmd = self.nav.cmd_string);
-        let (control, _shift, alt) = (
+        if key != "Tab" {
+            self.nav.completion = None;
+        }
+        let (control, shift, alt) = (
             ev.modifiers.contains(KeyModifiers::CONTROL),
             ev.modifiers.contains(KeyModifiers::SHIFT),
             ev.modifiers.contains(KeyModifiers::ALT),
@@ -217,8 +714,19 @@ impl App {
         if key == "Esc" || (control && (key == "c" || key == "[")) {
             self.normal_mode();
         } else if key == "Enter" {
+            self.nav.add_history();
             CmdAction::new(&self.nav.cmd_string, self.nav.cmd_prefix).run(self);
             self.normal_mode();
+        } else if key == "Tab" {
+            let user_cmds: Vec<_> = self.keymap.commands.keys().map(|k| k.as_str()).collect();
+            self.nav
+                .complete(&user_cmds, ev.code != KeyCode::BackTab && !shift);
+        } else if key == "Up" || (control && key == "p") {
+            self.nav.history_step(true);
+        } else if key == "Down" || (control && key == "n") {
+            self.nav.history_step(false);
+        } else if control && key == "r" {
+            self.nav.history_search = Some(String::new());
         } else if (control && key == "Left") || (alt && key == "b") {
             let (_, new) = utils::find_word(&self.nav.cmd_string, self.nav.cmd_string_ind);
             self.nav.cmd_string_ind = new;
@@ -322,6 +830,8 @@ impl App {
     pub fn normal_mode(&mut self) {
         self.nav.cmd_prefix = char::default();
         self.nav.cmd_string.clear();
+        self.nav.reset_history();
+        self.nav.completion = None;
         self.ui.hide_cursor();
     }
 
@@ -336,6 +846,16 @@ impl App {
         log::info!("run_shell: running command `{cmd_str}`");
         match prefix {
             '$' | '!' => {
+                // NOTE: files renamed by the command(like `A`) are journaled, they are the
+                // selections or the file under the cursor(`$rust_tfm_fx`).
+                let files = match self.nav.selections.is_empty() {
+                    true => self.nav.cfile().into_iter().collect(),
+                    false => self.nav.selections.to_vec(),
+                };
+                let snapshot = journal::Snapshot::new(&files);
+                if let Some(tty) = utils::tty() {
+                    cmd.stdout(tty);
+                }
                 self.ui.suspend();
                 match cmd.status() {
                     Ok(status) => {
@@ -352,6 +872,7 @@ impl App {
                     utils::pause();
                 }
                 self.ui.resume();
+                self.nav.journal.record(snapshot.renames());
             }
             '&' => {
                 cmd.stdin(Stdio::null())
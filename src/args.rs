@@ -0,0 +1,169 @@
+use simplelog::LevelFilter;
+use std::path::PathBuf;
+
+use crate::settings;
+
+pub const USAGE: &str = "\
+Usage: tfm [OPTIONS] [PATH]
+
+Start tfm in PATH(default: current directory), if PATH is a file,
+open its parent directory and put the cursor on it.
+
+Options:
+  -c, --config <FILE>        use FILE instead of `$XDG_CONFIG_HOME/tfm/config`
+  -l, --log-level <LEVEL>    off, error, warn, info, debug or trace
+      --print-default-config print the default config file and exit
+      --last-dir-path <FILE> write the last directory to FILE on quit
+      --print-last-dir       print the last directory to stdout on quit
+      --choosefiles <FILE>   file picker mode, opening a file writes the
+                             selected files(or the current file) to FILE
+                             and quits, `-` means stdout
+      --choosedir <FILE>     write the last directory to FILE on quit,
+                             `-` means stdout
+  -0, --null                 separate the paths written by `--choosefiles`
+                             and `--choosedir` with NUL instead of newline
+      --restore              restore the last session(see `session` in the
+                             config file)
+      --remote <ID> <CMD>    send CMD(like `:cd /tmp`) to the running tfm
+                             whose id is ID(`$rust_tfm_id`) and exit
+      --init <SHELL>         print the cd-on-exit wrapper function for
+                             bash, zsh or fish and exit
+  -h, --help                 print this help and exit
+  -v, --version              print version and exit";
+
+#[derive(Debug, Default)]
+pub struct Args {
+    pub path: Option<PathBuf>,
+    pub config: Option<PathBuf>,
+    pub log_level: Option<LevelFilter>,
+    pub print_default_config: bool,
+    pub last_dir_path: Option<PathBuf>,
+    pub print_last_dir: bool,
+    pub choosefiles: Option<PathBuf>,
+    pub choosedir: Option<PathBuf>,
+    pub null: bool,
+    pub restore: bool,
+    pub remote: Option<(String, String)>,
+    pub init: Option<String>,
+    pub help: bool,
+    pub version: bool,
+}
+
+impl Args {
+    // NOTE: `args` doesn't contain the program name.
+    pub fn parse<I: Iterator<Item = String>>(mut args: I) -> Result<Self, String> {
+        let mut ret = Self::default();
+        let mut only_path = false;
+        while let Some(arg) = args.next() {
+            if only_path || !arg.starts_with('-') || arg == "-" {
+                if ret.path.is_some() {
+                    return Err(format!("unexpected argument `{arg}`"));
+                }
+                ret.path = Some(PathBuf::from(arg));
+                continue;
+            }
+            // NOTE: `--option=value` is the same as `--option value`
+            let (opt, inline_val) = match arg.split_once('=') {
+                Some((opt, val)) if opt.starts_with("--") => (opt, Some(val.to_string())),
+                _ => (arg.as_str(), None),
+            };
+            let mut value = || {
+                inline_val
+                    .clone()
+                    .or_else(|| args.next())
+                    .ok_or(format!("option `{opt}` requires a value"))
+            };
+            match opt {
+                "--" => only_path = true,
+                "-c" | "--config" => ret.config = Some(PathBuf::from(value()?)),
+                "-l" | "--log-level" => ret.log_level = Some(settings::parse_log_level(&value()?)?),
+                "--print-default-config" => ret.print_default_config = true,
+                "--last-dir-path" => ret.last_dir_path = Some(PathBuf::from(value()?)),
+                "--print-last-dir" => ret.print_last_dir = true,
+                "--choosefiles" => ret.choosefiles = Some(PathBuf::from(value()?)),
+                "--choosedir" => ret.choosedir = Some(PathBuf::from(value()?)),
+                "-0" | "--null" => ret.null = true,
+                "--restore" => ret.restore = true,
+                "--remote" => {
+                    let id = value()?;
+                    let cmd = args
+                        .next()
+                        .ok_or(format!("option `{opt}` requires an id and a command"))?;
+                    ret.remote = Some((id, cmd));
+                }
+                "--init" => {
+                    let shell = value()?;
+                    if init_script(&shell).is_none() {
+                        return Err(format!(
+                            "`--init` expects one of bash|zsh|fish, found `{shell}`"
+                        ));
+                    }
+                    ret.init = Some(shell);
+                }
+                "-h" | "--help" => ret.help = true,
+                "-v" | "--version" => ret.version = true,
+                _ => return Err(format!("unknown option `{opt}`")),
+            }
+        }
+        Ok(ret)
+    }
+}
+
+pub fn init_script(shell: &str) -> Option<&'static str> {
+    match shell {
+        "bash" | "zsh" => Some(include_str!("../script/init.sh")),
+        "fish" => Some(include_str!("../script/init.fish")),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Args, String> {
+        Args::parse(args.iter().map(|s| s.to_string()))
+    }
+
+    #[test]
+    fn test_parse() {
+        let args = parse(&["-c", "/tmp/config", "--log-level=info", "/tmp"]).unwrap();
+        assert_eq!(Some(PathBuf::from("/tmp/config")), args.config);
+        assert_eq!(Some(LevelFilter::Info), args.log_level);
+        assert_eq!(Some(PathBuf::from("/tmp")), args.path);
+
+        let args = parse(&["--init", "fish", "--last-dir-path=/tmp/lastdir"]).unwrap();
+        assert_eq!(Some("fish".to_string()), args.init);
+        assert_eq!(Some(PathBuf::from("/tmp/lastdir")), args.last_dir_path);
+
+        let args = parse(&["--choosefiles", "-", "-0"]).unwrap();
+        assert_eq!(Some(PathBuf::from("-")), args.choosefiles);
+        assert!(args.null);
+        assert!(parse(&["--restore"]).unwrap().restore);
+
+        let args = parse(&["--remote", "123", ":cd /tmp"]).unwrap();
+        assert_eq!(
+            Some(("123".to_string(), ":cd /tmp".to_string())),
+            args.remote
+        );
+
+        let args = parse(&["--", "-file"]).unwrap();
+        assert_eq!(Some(PathBuf::from("-file")), args.path);
+
+        assert!(
+            parse(&["--print-default-config"])
+                .unwrap()
+                .print_default_config
+        );
+    }
+
+    #[test]
+    fn test_parse_error() {
+        assert!(parse(&["--config"]).is_err());
+        assert!(parse(&["--log-level", "loud"]).is_err());
+        assert!(parse(&["--no-such-option"]).is_err());
+        assert!(parse(&["/tmp", "/usr"]).is_err());
+        assert!(parse(&["--init", "csh"]).is_err());
+        assert!(parse(&["--remote", "123"]).is_err());
+    }
+}
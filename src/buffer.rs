@@ -484,7 +484,7 @@ mod tests {
                     b: 255
                 },
                 bg: Color::Reset,
-                attrs: attrs,
+                attrs,
             }
         );
         st.parse_ansi_code("1");
@@ -498,7 +498,7 @@ mod tests {
                     b: 255
                 },
                 bg: Color::Reset,
-                attrs: attrs,
+                attrs,
             }
         );
         st.parse_ansi_code("48;5;34");
@@ -511,7 +511,7 @@ mod tests {
                     b: 255
                 },
                 bg: Color::AnsiValue(34),
-                attrs: attrs,
+                attrs,
             }
         );
         st.parse_ansi_code("0");
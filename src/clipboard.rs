@@ -0,0 +1,128 @@
+use crossbeam_channel::Sender;
+use std::{
+    path::{Path, PathBuf},
+    thread,
+    time::{Duration, SystemTime},
+};
+
+use crate::selections::Selections;
+use crate::utils;
+
+// NOTE: the cut/copy list shared by all tfm instances, like lf's `~/.local/share/lf/files`.
+// The first line is the mode(`copy` or `move`), and the rest lines are paths.
+//
+// move
+// /home/user/a.txt
+// /home/user/dir
+pub fn clipboard_file() -> PathBuf {
+    utils::data_dir().join("files")
+}
+
+pub fn save(is_cut: bool, paths: &Selections) -> std::io::Result<()> {
+    save_to(&clipboard_file(), is_cut, paths)
+}
+
+fn save_to(file: &Path, is_cut: bool, paths: &Selections) -> std::io::Result<()> {
+    let mut content = String::from(if is_cut { "move\n" } else { "copy\n" });
+    for p in paths.iter() {
+        content += &format!("{}\n", p.display());
+    }
+    utils::write_atomic(file, &content)
+}
+
+// NOTE: paths which don't exist any more are dropped.
+pub fn load() -> (bool, Selections) {
+    load_from(&clipboard_file())
+}
+
+fn load_from(file: &Path) -> (bool, Selections) {
+    let mut paths = Selections::new();
+    let content = match std::fs::read_to_string(file) {
+        Ok(content) => content,
+        Err(_) => return (false, paths),
+    };
+    let mut lines = content.lines();
+    let is_cut = match lines.next() {
+        Some("move") => true,
+        Some("copy") => false,
+        _ => {
+            log::error!("invalid clipboard file: the first line should be `copy` or `move`");
+            return (false, paths);
+        }
+    };
+    for line in lines {
+        let p = Path::new(line);
+        if !line.is_empty() && (p.exists() || p.is_symlink()) {
+            paths.insert(p.to_path_buf());
+        }
+    }
+    (is_cut, paths)
+}
+
+// Put a restored cut/copy list to the clipboard file, only if it's empty, since the list in it may
+// be used by other tfm instances. Returns whether the list is put.
+pub fn restore(is_cut: bool, paths: &Selections) -> std::io::Result<bool> {
+    restore_to(&clipboard_file(), is_cut, paths)
+}
+
+fn restore_to(file: &Path, is_cut: bool, paths: &Selections) -> std::io::Result<bool> {
+    if paths.is_empty() || !load_from(file).1.is_empty() {
+        return Ok(false);
+    }
+    save_to(file, is_cut, paths)?;
+    Ok(true)
+}
+
+fn modified(p: &Path) -> Option<(SystemTime, u64)> {
+    p.metadata()
+        .ok()
+        .and_then(|m| m.modified().ok().map(|t| (t, m.len())))
+}
+
+// Poll the clipboard file, and send `()` to `unit_send` when it's changed.
+pub fn watch(unit_send: Sender<()>) {
+    thread::spawn(move || {
+        let file = clipboard_file();
+        let mut last = modified(&file);
+        loop {
+            thread::sleep(Duration::from_millis(500));
+            let curr = modified(&file);
+            if curr != last {
+                last = curr;
+                if let Err(err) = unit_send.send(()) {
+                    log::error!("{err} => clipboard watch unit_send send failed");
+                    return;
+                }
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_restore() {
+        let dir = utils::TempDir::new("clipboard");
+        let file = dir.join("files");
+        let (a, b) = (dir.join("a"), dir.join("b"));
+        std::fs::write(&a, "a").unwrap();
+        std::fs::write(&b, "b").unwrap();
+        let list = |p: &Path| {
+            let mut paths = Selections::new();
+            paths.insert(p.to_path_buf());
+            paths
+        };
+
+        // NOTE: `paste` loads the clipboard file, so the restored list must be in it.
+        assert!(restore_to(&file, true, &list(&a)).unwrap());
+        let (is_cut, paths) = load_from(&file);
+        assert_eq!((true, vec![a.clone()]), (is_cut, paths.to_vec()));
+
+        // NOTE: a list put by other instances is kept
+        assert!(!restore_to(&file, false, &list(&b)).unwrap());
+        let (is_cut, paths) = load_from(&file);
+        assert_eq!((true, vec![a]), (is_cut, paths.to_vec()));
+    }
+}
@@ -0,0 +1,219 @@
+use std::{env, os::unix::fs::PermissionsExt};
+
+use crate::action::COMMANDS;
+use crate::utils;
+
+const SET_OPTIONS: &[&str] = &["hidden", "unhidden", "hidden!", "sortby"];
+const SORT_TYPES: &[&str] = &["natural", "mtime", "size"];
+
+// NOTE: these characters are escaped by `\` in completed paths.
+const SPECIAL_CHARS: &str = " \t\\'\"$;&|<>()`*?[]{}!#";
+
+// Tab completion state of the command line.
+pub struct Completion {
+    pub start: usize, // NOTE: byte index of the completed word in `nav.cmd_string`
+    pub candidates: Vec<String>,
+    pub ind: Option<usize>, // the candidate in the command line, None means their common prefix
+}
+
+// A word of the command line before the cursor: (byte index where it starts, text without `\`)
+fn split_words(prefix: char, line: &str) -> Vec<(usize, String)> {
+    let mut words = vec![(0, String::new())];
+    let mut escaped = false;
+    for (i, ch) in line.char_indices() {
+        let next = i + ch.len_utf8();
+        if escaped {
+            escaped = false;
+            words.last_mut().unwrap().1.push(ch);
+        } else if ch == '\\' {
+            escaped = true;
+        } else if ch == ';' || (prefix != ':' && ch == '|') {
+            // NOTE: a new command starts
+            words = vec![(next, String::new())];
+        } else if ch == ' ' || ch == '\t' {
+            words.push((next, String::new()));
+        } else {
+            words.last_mut().unwrap().1.push(ch);
+        }
+    }
+    // NOTE: blanks between words make empty words, only the last one is kept
+    let last = words.pop().unwrap();
+    words.retain(|(_, w)| !w.is_empty());
+    words.push(last);
+    words
+}
+
+// Candidates to replace the word before the cursor with, and where the word starts in `line`.
+pub fn candidates(prefix: char, line: &str, user_cmds: &[&str]) -> (usize, Vec<String>) {
+    let words = split_words(prefix, line);
+    let (start, word) = words.last().unwrap();
+    let args: Vec<_> = words.iter().map(|(_, w)| w.as_str()).collect();
+    let mut ret = match (prefix, &args[..]) {
+        (':', [_]) => starts_with(COMMANDS.iter().chain(user_cmds.iter()), word),
+        (':', ["set", _]) => starts_with(SET_OPTIONS.iter(), word),
+        (':', ["set", "sortby", _]) => starts_with(SORT_TYPES.iter(), word),
+        (':', ["cd", _]) => paths(word, true),
+        (':', ["rename", _]) => paths(word, false),
+        ('$' | '!' | '&', [_]) if !word.contains('/') => executables(word),
+        ('$' | '!' | '&', _) => paths(word, false),
+        _ => Vec::new(),
+    };
+    ret.sort();
+    ret.dedup();
+    (*start, ret)
+}
+
+fn starts_with<'a, I: Iterator<Item = &'a &'a str>>(iter: I, word: &str) -> Vec<String> {
+    iter.filter(|s| s.starts_with(word))
+        .map(|s| s.to_string())
+        .collect()
+}
+
+fn escape(s: &str) -> String {
+    let mut ret = String::new();
+    for ch in s.chars() {
+        if SPECIAL_CHARS.contains(ch) {
+            ret.push('\\');
+        }
+        ret.push(ch);
+    }
+    ret
+}
+
+// NOTE: directories end with `/`, and hidden files are listed only if `word` has a leading `.` name.
+fn paths(word: &str, only_dir: bool) -> Vec<String> {
+    let (dir, name) = match word.rfind('/') {
+        Some(i) => (&word[..=i], &word[i + 1..]),
+        None => ("", word),
+    };
+    let entries = match std::fs::read_dir(if dir.is_empty() {
+        ".".into()
+    } else {
+        utils::expand_tilde(dir)
+    }) {
+        Ok(entries) => entries,
+        Err(_) => return Vec::new(),
+    };
+    let mut ret = Vec::new();
+    for entry in entries.flatten() {
+        let file_name = entry.file_name().to_string_lossy().to_string();
+        if !file_name.starts_with(name) || (file_name.starts_with('.') && !name.starts_with('.')) {
+            continue;
+        }
+        // NOTE: follow symbolic links
+        let is_dir = entry.path().is_dir();
+        if only_dir && !is_dir {
+            continue;
+        }
+        let suffix = if is_dir { "/" } else { "" };
+        ret.push(format!("{}{suffix}", escape(&format!("{dir}{file_name}"))));
+    }
+    ret
+}
+
+fn executables(word: &str) -> Vec<String> {
+    let mut ret = Vec::new();
+    for dir in env::var("PATH").unwrap_or_default().split(':') {
+        if let Ok(entries) = std::fs::read_dir(dir) {
+            for entry in entries.flatten() {
+                let name = entry.file_name().to_string_lossy().to_string();
+                let executable = entry
+                    .path()
+                    .metadata()
+                    .is_ok_and(|m| m.is_file() && m.permissions().mode() & 0o111 != 0);
+                if name.starts_with(word) && executable {
+                    ret.push(escape(&name));
+                }
+            }
+        }
+    }
+    ret
+}
+
+// Longest common prefix of `candidates`, on char boundaries.
+pub fn common_prefix(candidates: &[String]) -> &str {
+    let first = match candidates.first() {
+        Some(first) => first.as_str(),
+        None => return "",
+    };
+    let mut len = first.len();
+    for c in &candidates[1..] {
+        len = first
+            .char_indices()
+            .zip(c.chars())
+            .take_while(|((_, a), b)| a == b)
+            .last()
+            .map_or(0, |((i, a), _)| i + a.len_utf8())
+            .min(len);
+    }
+    &first[..len]
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_candidates() {
+        assert_eq!(
+            (0, vec!["quit".to_string(), "quit!".to_string()]),
+            candidates(':', "qu", &[])
+        );
+        assert_eq!(
+            (
+                0,
+                vec!["cd".to_string(), "cmd".to_string(), "copy".to_string()]
+            ),
+            candidates(':', "c", &["copy"])
+        );
+        assert_eq!(
+            (4, vec!["hidden".to_string(), "hidden!".to_string()]),
+            candidates(':', "set h", &[])
+        );
+        assert_eq!(
+            (17, vec!["mtime".to_string()]),
+            candidates(':', "cd /; set sortby m", &[])
+        );
+        assert_eq!((6, Vec::<String>::new()), candidates(':', "quit  ", &[]));
+    }
+
+    #[test]
+    fn test_paths() {
+        let dir = utils::TempDir::new("complete");
+        std::fs::create_dir_all(dir.join("a dir")).unwrap();
+        std::fs::write(dir.join("a file"), "").unwrap();
+        std::fs::write(dir.join(".a"), "").unwrap();
+        let d = dir.display().to_string();
+
+        let line = format!("cd {d}/a");
+        assert_eq!(
+            (3, vec![format!("{}/a\\ dir/", escape(&d))]),
+            candidates(':', &line, &[])
+        );
+        let line = format!("ls {}/a\\ ", escape(&d));
+        assert_eq!(
+            (
+                3,
+                vec![
+                    format!("{}/a\\ dir/", escape(&d)),
+                    format!("{}/a\\ file", escape(&d))
+                ]
+            ),
+            candidates('$', &line, &[])
+        );
+        let line = format!("ls {d}/.");
+        assert_eq!(
+            (3, vec![format!("{}/.a", escape(&d))]),
+            candidates('!', &line, &[])
+        );
+    }
+
+    #[test]
+    fn test_common_prefix() {
+        let c = |v: &[&str]| v.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        assert_eq!("qu", common_prefix(&c(&["quit", "qux", "quit!"])));
+        assert_eq!("", common_prefix(&c(&["a", "b"])));
+        assert_eq!("中文", common_prefix(&c(&["中文a", "中文b"])));
+        assert_eq!("", common_prefix(&[]));
+    }
+}
@@ -1,6 +1,7 @@
 use simplelog::LevelFilter;
 use std::sync::RwLock;
 
+use crate::conflict::Conflict;
 use crate::dir::SortType;
 
 pub const LOG_LEVEL: LevelFilter = LevelFilter::Warn;
@@ -13,6 +14,11 @@ pub const RUST_TFM: &str = "(rust-tfm)";
 pub const SCROLL_OFF: u16 = 6;
 pub const CASE_INSENSITIVE: bool = true;
 pub const WORD_SEPS: &[char] = &['/', '.']; // And all non-printed characters
+pub const LS_COLORS: bool = true; // colour file entries using `LS_COLORS` env variable if it's set
+pub const HISTORY_SIZE: usize = 1000; // max number of commands kept in the history of each prefix
+pub const SESSION: bool = false; // true: save the session on quit and restore it on startup
+pub const PASTE_CONFLICT: Conflict = Conflict::Ask; // what `pp` does with existing files: ask, overwrite, skip, rename, overwrite-if-newer
+pub const MAX_JOBS: usize = 1; // max number of pastes and removes running at the same time, the others are queued
 
 // default
 pub static HIDDEN: RwLock<bool> = RwLock::new(true); // true: don't show hidden file by default
@@ -25,6 +31,7 @@ pub const OPENER: &str = "~/code/tfm/script/open";
 #[rustfmt::skip]
 pub mod actions {
     // NOTE: (&str, &str, &str): (`c` for CallAction and `s` for SetAction and `m` for CmdAction, keymap, operation)
+    // These are the default keys, they can be changed by `$XDG_CONFIG_HOME/tfm/keymap`, see `src/keymap.rs`.
     pub const KEYS: &[(&str, &str, &str)] = &[
         ("c", "j", "down"),
         ("c", "k", "up"),
@@ -57,24 +64,39 @@ pub mod actions {
         ("c", "c", "clear"),  // Clear `cut` or `copy` selection
 
         ("c", "pp", "paste"),   // built-in paste function(move or copy depending on `dd` or `yy`).
-        ("c", "DD", "remove"),  // NOTE: this is the built-in rm function. It's recommended using a `trash` instead of this function, like `dD` below does.
+        ("c", "pa", "paste_ask"),  // paste variants with a conflict policy other than `PASTE_CONFLICT`, see `src/conflict.rs`
+        ("c", "po", "paste_overwrite"),
+        ("c", "ps", "paste_skip"),
+        ("c", "pr", "paste_rename"),
+        ("c", "pn", "paste_newer"),
+        ("c", "DD", "remove"),  // NOTE: this is the built-in rm function. It's recommended using `trash` instead of this function, like `dD` does.
+        ("c", "dD", "trash"),  // move files to the freedesktop.org trash, `:trash-restore` lists them
+        ("c", "U", "undo"),  // undo the last rename, paste, trash, `:mkdir`, `:touch` or `:ln`
+        ("c", "<C-r>", "redo"),
+        ("c", "P", "pause_job"),  // pause the running jobs(`paste` and `remove`), press it again to resume, `:jobs` lists them
+        ("c", "X", "cancel_job"),  // cancel all the unfinished jobs, a running job stops after the current file
 
         ("c", "a", "rename"),  // rename file under the cursor
+
+        ("c", "m", "mark_save"),  // `m<char>` saves the current directory and the file under the cursor as mark `<char>`
+        ("c", "'", "mark_jump"),  // `'<char>` jumps to mark `<char>`, `:marks` lists all marks
+        ("c", "<C-o>", "jump_back"),  // go back to the previous directory in the jump list
+        ("c", "<Tab>", "jump_forward"),  // NOTE: terminals send `<C-i>` as `<Tab>`
+        ("m", "-", ":cd -"),  // go to the previous directory
         // --------------------------
         ("s", "zh", "hidden!"),  // toggle hidden files
         ("s", "zs", "sortby size"),  // sort by file size
         ("s", "zn", "sortby natural"),  // sort by natural comparison
         // --------------------------
         ("m", "gt", ":cd /tmp"),  // goto /tmp
-        ("m", "gh", ":cd /home/jsj"),  // goto home directory, NOTE: you need change to your own dir.
+        ("m", "gh", ":cd ~"),  // goto home directory
         ("m", "gu", ":cd /usr"),
-        ("m", "gc", ":cd /home/jsj/.config"),  // NOTE: you need change to your own dir.
-        ("m", "gl", ":cd /home/jsj/.local"),   // NOTE: you need change to your own dir.
+        ("m", "gc", ":cd ~/.config"),
+        ("m", "gl", ":cd ~/.local"),
         ("m", "gr", ":cd /"),
 
         ("m", "bg", "$set-bg \"$rust_tfm_f\""),  // NOTE: this is a bash script to set backgrounp only for myself, you can just delete it.
         ("m", "<Enter>", "$$EDITOR \"$rust_tfm_f\""),  // NOTE: Open a file using text editor, and don't care what file type it is.
-        ("m", "dD", "$IFS=$'\n';trashy put $rust_tfm_fx"),  // NOTE: using `trashy put` to remove files to Trash instead directly remove it.
         ("m", "A", CMD_CHANGE_FILES_NAME), // change files name using text editor, NOTE: when changing finished you need manually unselect previously selected files.
 
         ("m", "yp", "&echo -n \"$rust_tfm_f\" | xsel -ib"),  // copy file path under the cursor to system clipboard. NOTE: you need to change `xsel` to your clipboard manager(X11: xclip or xsel, Wayland: wl-clipboard)
@@ -105,6 +127,7 @@ pub mod actions {
     }"#;
 }
 
+// NOTE: These are the default styles, they can be changed by `$XDG_CONFIG_HOME/tfm/theme`, see `src/theme.rs`.
 #[rustfmt::skip]
 pub mod theme {
     use crate::buffer::{Attr, Attrs, Style};
@@ -0,0 +1,92 @@
+use std::path::Path;
+
+// What `paste` does when a file with the same name already exists in the target directory.
+//
+// - `ask`: ask for every conflicting file, `y` overwrites, `n` keeps both and `a` overwrites all
+// - `overwrite`: replace the existing file, directories are merged
+// - `skip`: don't paste the file
+// - `rename`: keep both, the pasted file is renamed to `<name>.~N~`
+// - `overwrite-if-newer`: like `overwrite` if the pasted file is newer, otherwise `skip`, and it's
+//   checked for every file when merging directories
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Conflict {
+    Ask,
+    Overwrite,
+    Skip,
+    Rename,
+    OverwriteIfNewer,
+}
+
+impl Conflict {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "ask" => Some(Self::Ask),
+            "overwrite" => Some(Self::Overwrite),
+            "skip" => Some(Self::Skip),
+            "rename" => Some(Self::Rename),
+            "overwrite-if-newer" => Some(Self::OverwriteIfNewer),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Ask => "ask",
+            Self::Overwrite => "overwrite",
+            Self::Skip => "skip",
+            Self::Rename => "rename",
+            Self::OverwriteIfNewer => "overwrite-if-newer",
+        }
+    }
+}
+
+// NOTE: true if `dst` doesn't exist, symbolic links themselves are compared.
+pub fn is_newer(src: &Path, dst: &Path) -> bool {
+    let mtime = |p: &Path| p.symlink_metadata().and_then(|m| m.modified());
+    match (mtime(src), mtime(dst)) {
+        (Ok(src), Ok(dst)) => src > dst,
+        (_, Err(_)) => true,
+        _ => false,
+    }
+}
+
+// NOTE: directories are merged instead of being replaced, symbolic links to directories aren't.
+pub fn is_merge(src: &Path, dst: &Path) -> bool {
+    let is_dir = |p: &Path| p.symlink_metadata().is_ok_and(|m| m.is_dir());
+    is_dir(src) && is_dir(dst)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::time::{Duration, SystemTime};
+
+    #[test]
+    fn test_name() {
+        for name in ["ask", "overwrite", "skip", "rename", "overwrite-if-newer"] {
+            assert_eq!(name, Conflict::from_name(name).unwrap().name());
+        }
+        assert_eq!(None, Conflict::from_name("newer"));
+    }
+
+    #[test]
+    fn test_is_newer() {
+        let dir = crate::utils::TempDir::new("conflict");
+        let (old, new) = (dir.join("old"), dir.join("new"));
+        let now = SystemTime::now();
+        std::fs::File::create(&old)
+            .unwrap()
+            .set_modified(now - Duration::from_secs(60))
+            .unwrap();
+        std::fs::File::create(&new)
+            .unwrap()
+            .set_modified(now)
+            .unwrap();
+        assert!(is_newer(&new, &old));
+        assert!(!is_newer(&old, &new));
+        assert!(!is_newer(&new, &new));
+        assert!(is_newer(&old, &dir.join("none")));
+        assert!(is_merge(&dir, &dir));
+        assert!(!is_merge(&dir, &new));
+    }
+}
@@ -3,8 +3,9 @@ use std::{
     time::SystemTime,
 };
 
-use crate::config::{HIDDEN, SCROLL_OFF, SORT_TYPE};
+use crate::config::{HIDDEN, SORT_TYPE};
 use crate::file::File;
+use crate::settings::settings;
 use crate::utils;
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
@@ -15,6 +16,23 @@ pub enum SortType {
 }
 
 impl SortType {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "natural" => Some(Self::Natural),
+            "mtime" => Some(Self::ModifyTime),
+            "size" => Some(Self::Size),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Natural => "natural",
+            Self::ModifyTime => "mtime",
+            Self::Size => "size",
+        }
+    }
+
     pub fn sort(&self, files: &mut [File]) {
         match self {
             Self::Natural => Self::natural_sort(files),
@@ -28,11 +46,11 @@ impl SortType {
     }
 
     fn modify_time_sort(files: &mut [File]) {
-        files.sort_by(|a, b| a.mtime.cmp(&b.mtime));
+        files.sort_by_key(|a| a.mtime);
     }
 
     fn size_sort(files: &mut [File]) {
-        files.sort_by(|a, b| a.size.cmp(&b.size));
+        files.sort_by_key(|a| a.size);
     }
 }
 
@@ -185,12 +203,21 @@ impl Dir {
                     }
                 }
             }
-            if ind <= self.sp {
-                self.sp = 0;
-            }
-            self.bp = ind - self.sp;
-            self.bound_position(rows);
+            self.goto(ind, rows);
+        }
+    }
+
+    // Put the cursor on the `ind`th file.
+    pub fn goto(&mut self, ind: usize, rows: usize) {
+        if self.files_len == 0 {
+            return;
+        }
+        let ind = std::cmp::min(ind, self.files_len - 1);
+        if ind <= self.sp {
+            self.sp = 0;
         }
+        self.bp = ind - self.sp;
+        self.bound_position(rows);
     }
 
     pub fn bound_position(&mut self, rows: usize) {
@@ -208,10 +235,11 @@ impl Dir {
         }
         log::info!("bp: {bp}, files_len: {files_len}", bp = self.bp);
         if files_len > rows {
-            let scroll_off = if rows % 2 == 0 {
-                std::cmp::min(SCROLL_OFF as usize, rows / 2 - 1)
+            let scroll_off = settings().scroll_off as usize;
+            let scroll_off = if rows.is_multiple_of(2) {
+                std::cmp::min(scroll_off, rows / 2 - 1)
             } else {
-                std::cmp::min(SCROLL_OFF as usize, rows / 2)
+                std::cmp::min(scroll_off, rows / 2)
             };
 
             if self.bp <= scroll_off && self.sp > 0 {
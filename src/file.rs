@@ -7,6 +7,8 @@ use std::{
 
 use chrono::{prelude::DateTime, Local};
 
+use crate::utils;
+
 #[derive(PartialEq, Eq, Debug)]
 pub enum LinkState {
     Working(String),
@@ -127,23 +129,7 @@ impl File {
     }
 
     pub fn size(&self) -> String {
-        let unit = &['B', 'K', 'M', 'G'];
-        let mut s = self.size;
-        let mut divisor = 1f64;
-        let mut i = 0usize;
-        while i < unit.len() - 1 {
-            if s < 1024 {
-                break;
-            }
-            s >>= 10;
-            divisor *= 1024f64;
-            i += 1;
-        }
-        format!(
-            "{size:.1}{unit}",
-            size = self.size as f64 / divisor,
-            unit = unit[i]
-        )
+        utils::human_size(self.size)
     }
 
     pub fn mode(&self) -> String {
@@ -191,6 +177,14 @@ impl File {
         self.file_type == FileType::RegularFile && self.mode & 0o111 != 0
     }
 
+    pub fn is_setuid(&self) -> bool {
+        self.mode & 0o4000 != 0
+    }
+
+    pub fn is_setgid(&self) -> bool {
+        self.mode & 0o2000 != 0
+    }
+
     pub fn file_info(&self) -> String {
         format!(
             "{p} {s} {u} {g} {mtime}",
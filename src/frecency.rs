@@ -0,0 +1,256 @@
+use std::{
+    collections::HashMap,
+    fmt::Display,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::utils;
+
+// NOTE: when the sum of all ranks exceeds it, all ranks are multiplied by 0.9 and
+// directories whose rank drop below 1 are removed.
+const MAX_TOTAL_RANK: f64 = 10000.0;
+
+const HOUR: u64 = 60 * 60;
+const DAY: u64 = 24 * HOUR;
+const WEEK: u64 = 7 * DAY;
+
+// NOTE: directories visited by all tfm instances, one per line: `<rank>\t<last visit time>\t<path>`
+//
+// 12.5	1700000000	/home/user/Downloads
+pub fn frecency_file() -> PathBuf {
+    utils::data_dir().join("dirs")
+}
+
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Entry {
+    rank: f64,
+    last: u64,
+}
+
+impl Entry {
+    // frequency weighted by recency, like zoxide
+    fn score(&self, now: u64) -> f64 {
+        let age = now.saturating_sub(self.last);
+        self.rank
+            * if age < HOUR {
+                4.0
+            } else if age < DAY {
+                2.0
+            } else if age < WEEK {
+                0.5
+            } else {
+                0.25
+            }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Change {
+    Visit(PathBuf, u64),
+    Forget(PathBuf),
+}
+
+#[derive(Debug, Default)]
+pub struct Frecency {
+    dirs: HashMap<PathBuf, Entry>,
+    // NOTE: changes since loaded, replayed on the file when saving, since other tfm instances
+    // may have saved it in the meantime.
+    changes: Vec<Change>,
+}
+
+impl Frecency {
+    // NOTE: invalid lines are ignored
+    pub fn parse(content: &str) -> Self {
+        let mut dirs = HashMap::new();
+        for line in content.lines() {
+            let fields: Vec<_> = line.splitn(3, '\t').collect();
+            match fields[..] {
+                [rank, last, path] if !path.is_empty() => {
+                    match (rank.parse::<f64>(), last.parse::<u64>()) {
+                        (Ok(rank), Ok(last)) => {
+                            dirs.insert(PathBuf::from(path), Entry { rank, last });
+                        }
+                        _ => log::warn!("invalid directory history `{line}`"),
+                    }
+                }
+                _ => log::warn!("invalid directory history `{line}`"),
+            }
+        }
+        Self {
+            dirs,
+            changes: Vec::new(),
+        }
+    }
+
+    pub fn load() -> Self {
+        match std::fs::read_to_string(frecency_file()) {
+            Ok(content) => Self::parse(&content),
+            Err(_) => Self::default(),
+        }
+    }
+
+    // NOTE: merges the changes into the current file content, does nothing without changes.
+    pub fn save(&mut self) -> std::io::Result<()> {
+        if self.changes.is_empty() {
+            return Ok(());
+        }
+        let mut merged = Self::load();
+        for change in &self.changes {
+            merged.apply(change);
+        }
+        utils::write_atomic(&frecency_file(), &merged.to_string())?;
+        self.dirs = merged.dirs;
+        self.changes.clear();
+        Ok(())
+    }
+
+    pub fn add(&mut self, p: &Path, now: u64) {
+        let change = Change::Visit(p.to_path_buf(), now);
+        self.apply(&change);
+        self.changes.push(change);
+    }
+
+    pub fn remove(&mut self, p: &Path) -> bool {
+        let change = Change::Forget(p.to_path_buf());
+        let removed = self.apply(&change);
+        self.changes.push(change);
+        removed
+    }
+
+    // NOTE: returns false if a forgotten directory isn't in the set
+    fn apply(&mut self, change: &Change) -> bool {
+        match change {
+            Change::Visit(p, now) => {
+                let entry = self.dirs.entry(p.clone()).or_insert(Entry {
+                    rank: 0.0,
+                    last: *now,
+                });
+                entry.rank += 1.0;
+                entry.last = *now;
+                self.age(p);
+                true
+            }
+            Change::Forget(p) => self.dirs.remove(p).is_some(),
+        }
+    }
+
+    // NOTE: `visited` is kept even if its rank drops below 1, otherwise a first visit
+    // which triggers aging would be lost.
+    fn age(&mut self, visited: &Path) {
+        let total: f64 = self.dirs.values().map(|e| e.rank).sum();
+        if total > MAX_TOTAL_RANK {
+            self.dirs.retain(|p, e| {
+                e.rank *= 0.9;
+                e.rank >= 1.0 || p == visited
+            });
+        }
+    }
+
+    // Directories matching all `fragments`(ignoring case) in order, and the last fragment
+    // should match the last component. The best match comes first.
+    pub fn query(&self, fragments: &[&str], now: u64) -> Vec<(PathBuf, f64)> {
+        let fragments: Vec<_> = fragments.iter().map(|f| f.to_lowercase()).collect();
+        let mut ret: Vec<_> = self
+            .dirs
+            .iter()
+            .filter(|(p, _)| is_match(p, &fragments))
+            .map(|(p, e)| (p.clone(), e.score(now)))
+            .collect();
+        ret.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        ret
+    }
+}
+
+fn is_match(p: &Path, fragments: &[String]) -> bool {
+    let path = p.to_string_lossy().to_lowercase();
+    if let Some(last) = fragments.last() {
+        let name = p
+            .file_name()
+            .map(|n| n.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        if !name.contains(last.as_str()) {
+            return false;
+        }
+    }
+    let mut rest = path.as_str();
+    for f in fragments {
+        match rest.find(f.as_str()) {
+            Some(i) => rest = &rest[i + f.len()..],
+            None => return false,
+        }
+    }
+    true
+}
+
+impl Display for Frecency {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut dirs: Vec<_> = self.dirs.iter().collect();
+        dirs.sort_by(|a, b| a.0.cmp(b.0));
+        for (p, e) in dirs {
+            writeln!(f, "{}\t{}\t{}", e.rank, e.last, p.display())?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_query() {
+        let now = 10_000_000;
+        let content = format!(
+            "10\t{old}\t/home/user/src/tfm\n2\t{now}\t/tmp/tfm\n1\t{now}\t/home/user/src/foo\ninvalid\n",
+            old = now - 2 * WEEK
+        );
+        let mut f = Frecency::parse(&content);
+        let paths = |v: Vec<(PathBuf, f64)>| v.into_iter().map(|(p, _)| p).collect::<Vec<_>>();
+
+        // 2 * 4.0 > 10 * 0.25
+        assert_eq!(
+            vec![
+                PathBuf::from("/tmp/tfm"),
+                PathBuf::from("/home/user/src/tfm")
+            ],
+            paths(f.query(&["TFM"], now))
+        );
+        assert_eq!(
+            vec![PathBuf::from("/home/user/src/tfm")],
+            paths(f.query(&["src", "tfm"], now))
+        );
+        // the last fragment should match the last component
+        assert!(f.query(&["src"], now).is_empty());
+
+        f.add(Path::new("/home/user/src/foo"), now);
+        assert_eq!(2.0, f.dirs[Path::new("/home/user/src/foo")].rank);
+        assert_eq!(3, Frecency::parse(&f.to_string()).dirs.len());
+    }
+
+    #[test]
+    fn test_age() {
+        let mut f = Frecency::default();
+        f.dirs.insert(
+            PathBuf::from("/a"),
+            Entry {
+                rank: MAX_TOTAL_RANK,
+                last: 0,
+            },
+        );
+        f.dirs
+            .insert(PathBuf::from("/c"), Entry { rank: 1.0, last: 0 });
+        f.add(Path::new("/b"), 0);
+        assert!(f.dirs[Path::new("/a")].rank < MAX_TOTAL_RANK);
+        assert!(!f.dirs.contains_key(Path::new("/c")));
+        // NOTE: the visit which triggers aging is kept
+        assert_eq!(0.9, f.dirs[Path::new("/b")].rank);
+    }
+}
@@ -0,0 +1,116 @@
+use std::{collections::HashMap, fmt::Display, path::PathBuf};
+
+use crate::utils;
+
+const PREFIXES: &str = ":$!&/?";
+
+// NOTE: the command line history shared by all tfm instances, one command with its prefix per line,
+// from the oldest to the newest.
+//
+// :cd /tmp
+// $mkdir foo
+// /foo
+pub fn history_file() -> PathBuf {
+    utils::data_dir().join("history")
+}
+
+// History of each command prefix(`:`, `$`, `!`, `&`, `/` and `?`).
+#[derive(Debug, Default, PartialEq)]
+pub struct History {
+    entries: HashMap<char, Vec<String>>,
+}
+
+impl History {
+    // NOTE: lines with an unknown prefix are ignored
+    pub fn parse(content: &str) -> Self {
+        let mut history = Self::default();
+        for line in content.lines() {
+            let mut chars = line.chars();
+            match chars.next() {
+                Some(prefix) if PREFIXES.contains(prefix) && !chars.as_str().is_empty() => {
+                    history
+                        .entries
+                        .entry(prefix)
+                        .or_default()
+                        .push(chars.as_str().to_string());
+                }
+                _ => log::warn!("invalid history line `{line}`"),
+            }
+        }
+        history
+    }
+
+    pub fn load() -> Self {
+        match std::fs::read_to_string(history_file()) {
+            Ok(content) => Self::parse(&content),
+            Err(_) => Self::default(),
+        }
+    }
+
+    pub fn save(&self) -> std::io::Result<()> {
+        utils::write_atomic(&history_file(), &self.to_string())
+    }
+
+    // NOTE: a duplicate command is moved to the newest, and only the newest `size` commands are kept.
+    pub fn add(&mut self, prefix: char, cmd: &str, size: usize) {
+        if cmd.is_empty() || cmd.contains('\n') {
+            return;
+        }
+        let entries = self.entries.entry(prefix).or_default();
+        entries.retain(|e| e != cmd);
+        entries.push(cmd.to_string());
+        if entries.len() > size {
+            entries.drain(..entries.len() - size);
+        }
+    }
+
+    pub fn get(&self, prefix: char) -> &[String] {
+        self.entries.get(&prefix).map_or(&[], |e| e.as_slice())
+    }
+
+    // Index of the newest command before `before` containing `query`.
+    pub fn search(&self, prefix: char, query: &str, before: usize) -> Option<usize> {
+        let entries = self.get(prefix);
+        entries[..before.min(entries.len())]
+            .iter()
+            .rposition(|e| e.contains(query))
+    }
+}
+
+impl Display for History {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for prefix in PREFIXES.chars() {
+            for cmd in self.get(prefix) {
+                writeln!(f, "{prefix}{cmd}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_add() {
+        let mut h = History::parse(":cd /tmp\n$ls\ninvalid\n:set hidden!\n");
+        assert_eq!(&["cd /tmp", "set hidden!"], h.get(':'));
+        h.add(':', "cd /tmp", 10);
+        assert_eq!(&["set hidden!", "cd /tmp"], h.get(':'));
+        h.add(':', "quit", 2);
+        assert_eq!(&["cd /tmp", "quit"], h.get(':'));
+        assert_eq!(&["ls"], h.get('$'));
+        assert!(h.get('/').is_empty());
+        assert_eq!(h, History::parse(&h.to_string()));
+    }
+
+    #[test]
+    fn test_search() {
+        let h = History::parse(":cd /tmp\n:cd /usr\n:set hidden!\n");
+        assert_eq!(Some(1), h.search(':', "cd", 3));
+        assert_eq!(Some(0), h.search(':', "cd", 1));
+        assert_eq!(None, h.search(':', "cd", 0));
+        assert_eq!(None, h.search('$', "cd", 3));
+    }
+}
@@ -0,0 +1,676 @@
+use crossbeam_channel::{Receiver, Sender, TryRecvError};
+use std::{
+    cell::{Cell, RefCell},
+    collections::VecDeque,
+    path::{Path, PathBuf},
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::journal::Op;
+use crate::settings::settings;
+use crate::utils;
+
+const MAX_FINISHED: usize = 20; // NOTE: finished jobs kept for `:jobs`
+const REPORT_INTERVAL: Duration = Duration::from_millis(200); // NOTE: progress reports of a job thread
+const SPEED_WINDOW: Duration = Duration::from_secs(5); // NOTE: the speed is averaged over it
+const MAX_FILE_NAME: usize = 24; // NOTE: longer file names are cut in the status line
+
+// Commands sent to a job thread.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Ctl {
+    Start,
+    Pause,
+    Resume,
+    Cancel,
+}
+
+// What a job thread reports, tagged with the job id.
+#[derive(Debug)]
+pub enum Event {
+    Total(usize, u64), // NOTE: files and bytes of all the sources, sent once the job is started
+    Progress(usize, u64), // NOTE: files and bytes done since the last report
+    File(PathBuf),     // NOTE: the file being copied or removed
+    Error(String),
+    Finished {
+        ops: Vec<Op>,
+        done: usize, // NOTE: number of the sources handled
+        canceled: bool,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    Copy,
+    Move,
+    Remove,
+}
+
+impl Kind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Copy => "copy",
+            Self::Move => "move",
+            Self::Remove => "remove",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum State {
+    Queued,
+    Running,
+    Paused,
+    Done,
+    Canceled,
+}
+
+impl State {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Queued => "queued",
+            Self::Running => "running",
+            Self::Paused => "paused",
+            Self::Done => "done",
+            Self::Canceled => "canceled",
+        }
+    }
+
+    pub fn is_active(&self) -> bool {
+        matches!(self, Self::Running | Self::Paused)
+    }
+
+    pub fn is_finished(&self) -> bool {
+        matches!(self, Self::Done | Self::Canceled)
+    }
+}
+
+// A `paste` or `remove`, the files are handled by its own thread.
+pub struct Job {
+    pub id: usize,
+    pub kind: Kind,
+    pub sources: Vec<PathBuf>,
+    pub dst: Option<PathBuf>, // NOTE: None for `remove`
+    pub total: u64,
+    pub done: u64,
+    pub files: usize, // NOTE: count of the files under the sources
+    pub files_done: usize,
+    pub errors: Vec<String>,
+    pub state: State,
+    pub current: Option<PathBuf>,
+    started: Option<Instant>,
+    ended: Option<Instant>,
+    samples: VecDeque<(Instant, u64)>, // NOTE: (time, bytes done) within `SPEED_WINDOW`
+    ctl_send: Sender<Ctl>,
+}
+
+impl Job {
+    fn send(&self, ctl: Ctl) {
+        if let Err(err) = self.ctl_send.send(ctl) {
+            log::error!("{err} => job {id} ctl_send send failed", id = self.id);
+        }
+    }
+
+    pub fn percent(&self) -> f64 {
+        match self.total {
+            0 => 0.0,
+            total => self.done as f64 / total as f64 * 100.0,
+        }
+    }
+
+    fn add_done(&mut self, size: u64, now: Instant) {
+        self.done += size;
+        self.samples.push_back((now, self.done));
+        while self
+            .samples
+            .front()
+            .is_some_and(|(t, _)| now.duration_since(*t) > SPEED_WINDOW)
+        {
+            self.samples.pop_front();
+        }
+    }
+
+    // NOTE: bytes per second of the last few seconds, it drops while nothing is copied.
+    pub fn speed(&self) -> Option<f64> {
+        self.speed_at(Instant::now())
+    }
+
+    fn speed_at(&self, now: Instant) -> Option<f64> {
+        if self.state != State::Running {
+            return None;
+        }
+        // NOTE: it's counted from the start until there are two reports
+        let (t, done) = match self.samples.front() {
+            Some(&(t, done)) if self.samples.len() > 1 => (t, done),
+            _ => (self.started?, 0),
+        };
+        let secs = now.saturating_duration_since(t).as_secs_f64();
+        (secs > 0.0).then(|| (self.done - done) as f64 / secs)
+    }
+
+    // NOTE: estimated time left
+    pub fn eta(&self) -> Option<Duration> {
+        self.eta_at(Instant::now())
+    }
+
+    fn eta_at(&self, now: Instant) -> Option<Duration> {
+        match self.speed_at(now)? {
+            speed if speed < 1.0 => None,
+            speed => Some(Duration::from_secs_f64(
+                self.total.saturating_sub(self.done) as f64 / speed,
+            )),
+        }
+    }
+
+    // NOTE: one line of `:jobs`
+    pub fn summary(&self) -> String {
+        let mut s = format!(
+            "#{id} {kind:<6} {state:<8}",
+            id = self.id,
+            kind = self.kind.name(),
+            state = self.state.name()
+        );
+        if self.total != 0 {
+            s += &format!(
+                " {p:>3.0}% {done}/{total}",
+                p = self.percent(),
+                done = utils::human_size(self.done),
+                total = utils::human_size(self.total)
+            );
+        }
+        if let Some(speed) = self.speed() {
+            s += &format!(" {}/s", utils::human_size(speed as u64));
+        }
+        s += &format!(" {n} files", n = self.sources.len());
+        if let Some(ref dst) = self.dst {
+            s += &format!(" -> {}", dst.display());
+        }
+        if !self.errors.is_empty() {
+            s += &format!(" ({n} errors)", n = self.errors.len());
+        }
+        s
+    }
+
+    // NOTE: (value, text) of the job detail view, the value is a path to jump to.
+    pub fn details(&self) -> Vec<(String, String)> {
+        let mut lines = vec![(String::new(), self.summary())];
+        let mut times = Vec::new();
+        if let Some(speed) = self.speed() {
+            times.push(format!("speed: {}/s", utils::human_size(speed as u64)));
+        }
+        if let Some(eta) = self.eta() {
+            times.push(format!("time left: {}", utils::human_duration(eta)));
+        }
+        if let Some(started) = self.started {
+            let end = self.ended.unwrap_or_else(Instant::now);
+            let elapsed = end.duration_since(started);
+            times.push(format!("elapsed: {}", utils::human_duration(elapsed)));
+        }
+        if !times.is_empty() {
+            lines.push((String::new(), times.join("  ")));
+        }
+        if let Some(ref p) = self.current {
+            let p = p.display().to_string();
+            lines.push((p.clone(), format!("current: {p}")));
+        }
+        if let Some(ref dst) = self.dst {
+            let dst = dst.display().to_string();
+            lines.push((dst.clone(), format!("destination: {dst}")));
+        }
+        lines.push((String::new(), "sources:".to_string()));
+        for p in &self.sources {
+            let p = p.display().to_string();
+            lines.push((p.clone(), format!("  {p}")));
+        }
+        if !self.errors.is_empty() {
+            lines.push((String::new(), "errors:".to_string()));
+            for err in &self.errors {
+                lines.push((String::new(), format!("  {err}")));
+            }
+        }
+        lines
+    }
+}
+
+// The job thread's side, progress is reported at most every `REPORT_INTERVAL`.
+pub struct Worker {
+    id: usize,
+    ctl_recv: Receiver<Ctl>,
+    event_send: Sender<(usize, Event)>,
+    files: Cell<usize>,             // NOTE: files done but not reported yet
+    size: Cell<u64>,                // NOTE: bytes done but not reported yet
+    file: RefCell<Option<PathBuf>>, // NOTE: the current file if it's not reported yet
+    reported: Cell<Instant>,
+    finished: Cell<bool>,
+}
+
+impl Worker {
+    pub fn send(&self, event: Event) {
+        if let Err(err) = self.event_send.send((self.id, event)) {
+            log::error!("{err} => job {id} event_send send failed", id = self.id);
+        }
+    }
+
+    fn report(&self, force: bool) {
+        if !force && self.reported.get().elapsed() < REPORT_INTERVAL {
+            return;
+        }
+        if let Some(p) = self.file.take() {
+            self.send(Event::File(p));
+        }
+        let (files, size) = (self.files.take(), self.size.take());
+        if files != 0 || size != 0 {
+            self.send(Event::Progress(files, size));
+        }
+        self.reported.set(Instant::now());
+    }
+
+    // NOTE: `files` files of `size` bytes are copied or removed
+    pub fn progress(&self, files: usize, size: u64) {
+        self.files.set(self.files.get() + files);
+        self.size.set(self.size.get() + size);
+        self.report(false);
+    }
+
+    pub fn file(&self, p: &Path) {
+        *self.file.borrow_mut() = Some(p.to_path_buf());
+        self.report(false);
+    }
+
+    pub fn finish(&self, ops: Vec<Op>, done: usize, canceled: bool) {
+        self.finished.set(true);
+        self.report(true);
+        self.send(Event::Finished {
+            ops,
+            done,
+            canceled,
+        });
+    }
+
+    pub fn error(&self, err_msg: String) {
+        log::error!("{err_msg}");
+        self.send(Event::Error(err_msg));
+    }
+
+    // NOTE: blocks until the job is started, false if it's canceled before.
+    pub fn wait_start(&self) -> bool {
+        loop {
+            match self.ctl_recv.recv() {
+                Ok(Ctl::Start) => return true,
+                Ok(Ctl::Pause | Ctl::Resume) => {}
+                Ok(Ctl::Cancel) | Err(_) => return false,
+            }
+        }
+    }
+
+    // NOTE: called between files, so a job stops cleanly. False if the job is canceled, and it
+    // blocks while the job is paused.
+    pub fn keep_going(&self) -> bool {
+        let mut paused = false;
+        loop {
+            let ctl = if paused {
+                self.ctl_recv.recv().ok()
+            } else {
+                match self.ctl_recv.try_recv() {
+                    Ok(ctl) => Some(ctl),
+                    Err(TryRecvError::Empty) => return true,
+                    Err(TryRecvError::Disconnected) => None,
+                }
+            };
+            match ctl {
+                Some(Ctl::Pause) => paused = true,
+                Some(Ctl::Resume | Ctl::Start) => paused = false,
+                // NOTE: tfm is quitting if the sender is dropped
+                Some(Ctl::Cancel) | None => return false,
+            }
+        }
+    }
+}
+
+// NOTE: a job thread which returns or panics(in debug builds, release builds abort) without
+// calling `finish` would leave its job running forever.
+impl Drop for Worker {
+    fn drop(&mut self) {
+        if !self.finished.get() {
+            if thread::panicking() {
+                self.error(format!("job {id} thread panicked", id = self.id));
+            }
+            self.finish(Vec::new(), 0, true);
+        }
+    }
+}
+
+// NOTE: jobs are started in order, at most `max_jobs` of them run at the same time.
+pub struct Jobs {
+    pub list: Vec<Job>,
+    next_id: usize,
+    pub job_chan: utils::JobChan,
+}
+
+impl Jobs {
+    pub fn new() -> Self {
+        Self {
+            list: Vec::new(),
+            next_id: 1,
+            job_chan: utils::JobChan::new(),
+        }
+    }
+
+    // NOTE: the job is queued, the thread must call `Worker::wait_start` first.
+    pub fn add(&mut self, kind: Kind, sources: Vec<PathBuf>, dst: Option<PathBuf>) -> Worker {
+        let (ctl_send, ctl_recv) = crossbeam_channel::unbounded();
+        let id = self.next_id;
+        self.next_id += 1;
+        self.list.push(Job {
+            id,
+            kind,
+            sources,
+            dst,
+            total: 0,
+            done: 0,
+            files: 0,
+            files_done: 0,
+            errors: Vec::new(),
+            state: State::Queued,
+            current: None,
+            started: None,
+            ended: None,
+            samples: VecDeque::new(),
+            ctl_send,
+        });
+        self.start_queued();
+        Worker {
+            id,
+            ctl_recv,
+            event_send: self.job_chan.job_send.clone(),
+            files: Cell::new(0),
+            size: Cell::new(0),
+            file: RefCell::new(None),
+            reported: Cell::new(Instant::now()),
+            finished: Cell::new(false),
+        }
+    }
+
+    fn start_queued(&mut self) {
+        let mut running = self.list.iter().filter(|j| j.state.is_active()).count();
+        for job in self.list.iter_mut() {
+            if running >= settings().max_jobs {
+                break;
+            }
+            if job.state == State::Queued {
+                job.send(Ctl::Start);
+                job.state = State::Running;
+                running += 1;
+            }
+        }
+    }
+
+    pub fn get(&self, id: usize) -> Option<&Job> {
+        self.list.iter().find(|j| j.id == id)
+    }
+
+    // NOTE: queued, running and paused jobs
+    pub fn pending(&self) -> impl Iterator<Item = &Job> {
+        self.list.iter().filter(|j| !j.state.is_finished())
+    }
+
+    // NOTE: files and bytes left of the unfinished jobs which have counted their sources, and the
+    // sources of the others which haven't.
+    pub fn remaining(&self) -> ((usize, u64), Vec<PathBuf>) {
+        let (mut left, mut uncounted) = ((0, 0), Vec::new());
+        for job in self.pending() {
+            if job.started.is_some() {
+                left.0 += job.files.saturating_sub(job.files_done);
+                left.1 += job.total.saturating_sub(job.done);
+            } else {
+                uncounted.extend(job.sources.iter().cloned());
+            }
+        }
+        (left, uncounted)
+    }
+
+    // NOTE: progress of the running and paused jobs in the status line, like
+    // `45% 12.3M/s 1:23 a.mp4 +2`, the last one is the number of queued jobs.
+    pub fn status(&self) -> Option<String> {
+        let active: Vec<_> = self.list.iter().filter(|j| j.state.is_active()).collect();
+        if active.is_empty() {
+            return None;
+        }
+        let done: u64 = active.iter().map(|j| j.done).sum();
+        let total: u64 = active.iter().map(|j| j.total).sum();
+        let mut s = match total {
+            0 => "0%".to_string(),
+            total => format!("{p:.0}%", p = done as f64 / total as f64 * 100.0),
+        };
+        if active.iter().all(|j| j.state == State::Paused) {
+            s += " paused";
+        } else {
+            let speed: f64 = active.iter().filter_map(|j| j.speed()).sum();
+            if speed >= 1.0 {
+                let eta = Duration::from_secs_f64(total.saturating_sub(done) as f64 / speed);
+                s += &format!(
+                    " {speed}/s {eta}",
+                    speed = utils::human_size(speed as u64),
+                    eta = utils::human_duration(eta)
+                );
+            }
+            let file = active
+                .iter()
+                .find_map(|j| j.current.as_ref().and_then(|p| p.file_name()));
+            if let Some(name) = file {
+                let name = name.to_string_lossy();
+                match name.char_indices().nth(MAX_FILE_NAME) {
+                    Some((i, _)) => s += &format!(" {}…", &name[..i]),
+                    None => s += &format!(" {name}"),
+                }
+            }
+        }
+        let queued = self.pending().filter(|j| j.state == State::Queued).count();
+        if queued != 0 {
+            s += &format!(" +{queued}");
+        }
+        Some(s)
+    }
+
+    // Pause a running job or resume a paused one.
+    pub fn pause(&mut self, id: usize) -> Result<(), String> {
+        let job = self
+            .list
+            .iter_mut()
+            .find(|j| j.id == id)
+            .ok_or(format!("No job #{id}"))?;
+        match job.state {
+            State::Running => {
+                job.send(Ctl::Pause);
+                job.state = State::Paused;
+            }
+            State::Paused => {
+                job.send(Ctl::Resume);
+                job.state = State::Running;
+            }
+            state => return Err(format!("Job #{id} is {state}", state = state.name())),
+        }
+        Ok(())
+    }
+
+    // NOTE: the file being handled is finished first, the job is marked as canceled once its
+    // thread reports `Finished`.
+    pub fn cancel(&mut self, id: usize) -> Result<(), String> {
+        match self.list.iter().find(|j| j.id == id) {
+            Some(job) if !job.state.is_finished() => {
+                job.send(Ctl::Cancel);
+                Ok(())
+            }
+            Some(job) => Err(format!("Job #{id} is {state}", state = job.state.name())),
+            None => Err(format!("No job #{id}")),
+        }
+    }
+
+    // NOTE: returns the finished job's operations to record in the journal, and the message to
+    // show in the status line if it's canceled.
+    pub fn handle(&mut self, id: usize, event: Event) -> Option<(Vec<Op>, Option<String>)> {
+        let job = self.list.iter_mut().find(|j| j.id == id)?;
+        match event {
+            Event::Total(files, total) => {
+                (job.files, job.total) = (files, total);
+                job.started = Some(Instant::now());
+            }
+            Event::Progress(files, size) => {
+                job.files_done += files;
+                job.add_done(size, Instant::now());
+            }
+            Event::File(p) => job.current = Some(p),
+            Event::Error(err) => job.errors.push(err),
+            Event::Finished {
+                ops,
+                done,
+                canceled,
+            } => {
+                (job.current, job.ended) = (None, Some(Instant::now()));
+                let msg = if canceled {
+                    job.state = State::Canceled;
+                    Some(format!(
+                        "`{kind}` #{id} canceled, {done} of {all} files done",
+                        kind = job.kind.name(),
+                        all = job.sources.len()
+                    ))
+                } else {
+                    job.state = State::Done;
+                    None
+                };
+                self.prune();
+                self.start_queued();
+                return Some((ops, msg));
+            }
+        }
+        None
+    }
+
+    fn prune(&mut self) {
+        let finished = self.list.iter().filter(|j| j.state.is_finished()).count();
+        let mut extra = finished.saturating_sub(MAX_FINISHED);
+        self.list.retain(|j| {
+            let remove = extra > 0 && j.state.is_finished();
+            if remove {
+                extra -= 1;
+            }
+            !remove
+        });
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_queue() {
+        let mut jobs = Jobs::new();
+        let first = jobs.add(Kind::Copy, vec![PathBuf::from("/a")], Some("/b".into()));
+        let second = jobs.add(Kind::Remove, vec![PathBuf::from("/c")], None);
+        assert!(first.wait_start());
+        assert_eq!(State::Queued, jobs.get(2).unwrap().state);
+
+        jobs.pause(1).unwrap();
+        assert!(jobs.pause(2).is_err());
+        assert_eq!(Some("0% paused +1".to_string()), jobs.status());
+        // NOTE: a paused worker waits for the next control, which cancels it here.
+        jobs.cancel(1).unwrap();
+        assert!(!first.keep_going());
+
+        // NOTE: the second job is started once the first one is finished.
+        first.finish(Vec::new(), 0, true);
+        let events: Vec<_> = jobs.job_chan.job_recv.try_iter().collect();
+        let msg = events
+            .into_iter()
+            .filter_map(|(id, event)| jobs.handle(id, event))
+            .find_map(|(_, msg)| msg);
+        assert!(msg.unwrap().contains("canceled"));
+        assert_eq!(State::Running, jobs.get(2).unwrap().state);
+        assert!(second.wait_start());
+        assert!(second.keep_going());
+    }
+
+    #[test]
+    fn test_report() {
+        let mut jobs = Jobs::new();
+        let worker = jobs.add(Kind::Remove, vec![PathBuf::from("/c")], None);
+        assert!(worker.wait_start());
+
+        // NOTE: the reports are merged, and all of them are sent before `Finished`.
+        worker.file(Path::new("/c"));
+        worker.progress(1, 10);
+        worker.progress(2, 20);
+        worker.finish(Vec::new(), 1, false);
+        let events: Vec<_> = jobs.job_chan.job_recv.try_iter().collect();
+        let size: u64 = events
+            .iter()
+            .map(|(_, e)| if let Event::Progress(_, n) = e { *n } else { 0 })
+            .sum();
+        assert_eq!(30, size);
+        assert!(matches!(events.last(), Some((_, Event::Finished { .. }))));
+        for (id, event) in events {
+            jobs.handle(id, event);
+        }
+        let job = jobs.get(1).unwrap();
+        assert_eq!(
+            (State::Done, 3, 30, None),
+            (job.state, job.files_done, job.done, job.current.clone())
+        );
+    }
+
+    #[test]
+    fn test_remaining() {
+        let mut jobs = Jobs::new();
+        let worker = jobs.add(Kind::Copy, vec![PathBuf::from("/a")], Some("/b".into()));
+        let _queued = jobs.add(Kind::Remove, vec![PathBuf::from("/c")], None);
+        assert!(worker.wait_start());
+        worker.send(Event::Total(5, 100));
+        worker.progress(2, 40);
+        worker.report(true);
+        let events: Vec<_> = jobs.job_chan.job_recv.try_iter().collect();
+        for (id, event) in events {
+            jobs.handle(id, event);
+        }
+        // NOTE: the queued job hasn't counted its sources yet
+        assert_eq!(((3, 60), vec![PathBuf::from("/c")]), jobs.remaining());
+    }
+
+    #[test]
+    fn test_speed() {
+        let mut jobs = Jobs::new();
+        let _worker = jobs.add(Kind::Copy, vec![PathBuf::from("/a")], Some("/b".into()));
+        let start = Instant::now();
+        let at = |secs| start + Duration::from_secs(secs);
+        let job = &mut jobs.list[0];
+        (job.total, job.started) = (100, Some(start));
+
+        // NOTE: it's counted from the start until there are two reports
+        job.add_done(10, at(1));
+        assert_eq!(Some(5.0), job.speed_at(at(2)));
+        job.add_done(30, at(3));
+        assert_eq!(Some(15.0), job.speed_at(at(3)));
+        assert_eq!(Some(Duration::from_secs(4)), job.eta_at(at(3)));
+        // NOTE: the reports older than `SPEED_WINDOW` are dropped
+        job.add_done(20, at(7));
+        assert_eq!(Some(5.0), job.speed_at(at(7)));
+        assert_eq!(Some(Duration::from_secs(8)), job.eta_at(at(7)));
+
+        job.state = State::Paused;
+        assert_eq!(None, job.speed_at(at(7)));
+    }
+
+    #[test]
+    fn test_drop() {
+        let mut jobs = Jobs::new();
+        let worker = jobs.add(Kind::Copy, vec![PathBuf::from("/a")], Some("/b".into()));
+        assert!(worker.wait_start());
+        // NOTE: a worker dropped without `finish`, like a panicked thread
+        drop(worker);
+        let events: Vec<_> = jobs.job_chan.job_recv.try_iter().collect();
+        for (id, event) in events {
+            jobs.handle(id, event);
+        }
+        assert_eq!(State::Canceled, jobs.get(1).unwrap().state);
+    }
+}
@@ -0,0 +1,388 @@
+use std::{
+    collections::HashMap,
+    fs,
+    os::unix::fs::MetadataExt,
+    path::{Path, PathBuf},
+};
+
+use crate::trash::{self, TrashEntry};
+use crate::utils;
+
+const MAX_UNDO: usize = 100;
+
+// NOTE: what a file looked like right after an operation, undoing a copy or a creation is refused
+// if the file has changed since then.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Stamp {
+    ino: u64,
+    len: u64,
+    mtime: i64,
+    mtime_nsec: i64,
+}
+
+pub fn stamp(p: &Path) -> Option<Stamp> {
+    let metadata = p.symlink_metadata().ok()?;
+    Some(Stamp {
+        ino: metadata.ino(),
+        len: metadata.len(),
+        mtime: metadata.mtime(),
+        mtime_nsec: metadata.mtime_nsec(),
+    })
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Created {
+    Dir,
+    File,
+    Symlink(PathBuf),  // target
+    HardLink(PathBuf), // target
+}
+
+// A completed file operation.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Op {
+    Rename {
+        from: PathBuf,
+        to: PathBuf,
+    }, // NOTE: moves are renames too
+    Copy {
+        from: PathBuf,
+        to: PathBuf,
+        stamp: Option<Stamp>,
+    },
+    Create {
+        path: PathBuf,
+        kind: Created,
+        stamp: Option<Stamp>,
+    },
+    Trash(TrashEntry),
+}
+
+// Inodes of some files, to find where a shell command(like `A`) has renamed them.
+pub struct Snapshot(Vec<(PathBuf, u64, u64)>); // path, dev, ino
+
+impl Snapshot {
+    pub fn new(paths: &[PathBuf]) -> Self {
+        let files = paths
+            .iter()
+            .filter_map(|p| {
+                let metadata = p.symlink_metadata().ok()?;
+                Some((p.clone(), metadata.dev(), metadata.ino()))
+            })
+            .collect();
+        Self(files)
+    }
+
+    // NOTE: a file which is gone is looked for in the directories of all the files by its inode,
+    // files moved to other directories or replaced by a copy aren't found.
+    pub fn renames(&self) -> Vec<Op> {
+        let is_same = |p: &Path, dev, ino| {
+            p.symlink_metadata()
+                .is_ok_and(|m| m.dev() == dev && m.ino() == ino)
+        };
+        let gone: Vec<_> = self
+            .0
+            .iter()
+            .filter(|(p, dev, ino)| !is_same(p, *dev, *ino))
+            .collect();
+        if gone.is_empty() {
+            return Vec::new();
+        }
+        let mut dirs: Vec<_> = self.0.iter().filter_map(|(p, ..)| p.parent()).collect();
+        dirs.sort();
+        dirs.dedup();
+        let mut found = HashMap::new();
+        let entries = dirs.iter().filter_map(|d| fs::read_dir(d).ok()).flatten();
+        for entry in entries.flatten() {
+            if let Ok(metadata) = entry.metadata() {
+                found.insert((metadata.dev(), metadata.ino()), entry.path());
+            }
+        }
+        gone.into_iter()
+            .filter_map(|(from, dev, ino)| {
+                let to = found.get(&(*dev, *ino))?;
+                Some(Op::Rename {
+                    from: from.clone(),
+                    to: to.clone(),
+                })
+            })
+            .collect()
+    }
+}
+
+fn exists(p: &Path) -> bool {
+    p.symlink_metadata().is_ok()
+}
+
+fn must_exist(p: &Path) -> Result<(), String> {
+    match exists(p) {
+        true => Ok(()),
+        false => Err(format!("`{}` doesn't exist any more", p.display())),
+    }
+}
+
+fn must_not_exist(p: &Path) -> Result<(), String> {
+    match exists(p) {
+        true => Err(format!("`{}` already exists", p.display())),
+        false => Ok(()),
+    }
+}
+
+fn must_be_unchanged(p: &Path, old: &Option<Stamp>) -> Result<(), String> {
+    must_exist(p)?;
+    match stamp(p) == *old {
+        true => Ok(()),
+        false => Err(format!("`{}` has changed since then", p.display())),
+    }
+}
+
+impl Op {
+    // NOTE: the file to put the cursor on after undoing or redoing it
+    pub fn path(&self) -> &Path {
+        match self {
+            Op::Rename { from, .. } | Op::Copy { from, .. } => from,
+            Op::Create { path, .. } => path,
+            Op::Trash(entry) => &entry.path,
+        }
+    }
+
+    // Err is the reason why it can't be undone safely.
+    fn check_undo(&self) -> Result<(), String> {
+        match self {
+            Op::Rename { from, to } => {
+                must_exist(to)?;
+                must_not_exist(from)
+            }
+            Op::Copy { to, stamp, .. } => must_be_unchanged(to, stamp),
+            Op::Create { path, stamp, .. } => must_be_unchanged(path, stamp),
+            Op::Trash(entry) => {
+                must_exist(&entry.file)?;
+                must_not_exist(&entry.path)
+            }
+        }
+    }
+
+    // NOTE: a copy is undone by moving it to the trash, and the returned Op is for redoing.
+    fn undo(&self) -> Result<Op, String> {
+        match self {
+            Op::Rename { from, to } => utils::move_path(to, from)
+                .map_err(|err| format!("{err}: move `{}` back failed", to.display()))?,
+            Op::Copy { to, .. } => {
+                trash::put(to)?;
+            }
+            Op::Create { path, kind, .. } => {
+                let res = match kind {
+                    Created::Dir => fs::remove_dir(path),
+                    _ => fs::remove_file(path),
+                };
+                res.map_err(|err| format!("{err}: remove `{}` failed", path.display()))?
+            }
+            Op::Trash(entry) => trash::restore(entry)?,
+        }
+        Ok(self.clone())
+    }
+
+    fn check_redo(&self) -> Result<(), String> {
+        match self {
+            Op::Rename { from, to } | Op::Copy { from, to, .. } => {
+                must_exist(from)?;
+                must_not_exist(to)
+            }
+            Op::Create { path, .. } => must_not_exist(path),
+            Op::Trash(entry) => must_exist(&entry.path),
+        }
+    }
+
+    // NOTE: the returned Op is for undoing, the copied or created file has a new stamp, and the
+    // trashed file has a new trash entry.
+    fn redo(&self) -> Result<Op, String> {
+        let ret = match self {
+            Op::Rename { from, to } => {
+                utils::move_path(from, to)
+                    .map_err(|err| format!("{err}: move `{}` failed", from.display()))?;
+                self.clone()
+            }
+            Op::Copy { from, to, .. } => {
+                utils::copy_path(from, to)
+                    .map_err(|err| format!("{err}: copy `{}` failed", from.display()))?;
+                Op::Copy {
+                    from: from.clone(),
+                    to: to.clone(),
+                    stamp: stamp(to),
+                }
+            }
+            Op::Create { path, kind, .. } => {
+                let res = match kind {
+                    Created::Dir => fs::create_dir(path),
+                    Created::File => fs::File::create_new(path).map(|_| ()),
+                    Created::Symlink(target) => std::os::unix::fs::symlink(target, path),
+                    Created::HardLink(target) => fs::hard_link(target, path),
+                };
+                res.map_err(|err| format!("{err}: create `{}` failed", path.display()))?;
+                Op::Create {
+                    path: path.clone(),
+                    kind: kind.clone(),
+                    stamp: stamp(path),
+                }
+            }
+            Op::Trash(entry) => Op::Trash(trash::put(&entry.path)?),
+        };
+        Ok(ret)
+    }
+}
+
+// Completed operations, an entry is a group of operations done by one action(like a paste), it's
+// undone and redone as a whole.
+#[derive(Default)]
+pub struct Journal {
+    undo: Vec<Vec<Op>>,
+    redo: Vec<Vec<Op>>,
+}
+
+impl Journal {
+    pub fn record(&mut self, ops: Vec<Op>) {
+        if ops.is_empty() {
+            return;
+        }
+        self.undo.push(ops);
+        if self.undo.len() > MAX_UNDO {
+            self.undo.remove(0);
+        }
+        self.redo.clear();
+    }
+
+    // NOTE: nothing is done if any operation of the group can't be undone, and the path of the first
+    // operation is returned.
+    pub fn undo(&mut self) -> Result<PathBuf, String> {
+        let ops = self.undo.pop().ok_or("Nothing to undo")?;
+        if let Err(err) = ops.iter().rev().try_for_each(|op| op.check_undo()) {
+            self.undo.push(ops);
+            return Err(format!("Can't undo: {err}"));
+        }
+        let path = ops[0].path().to_path_buf();
+        let (mut done, mut err) = (Vec::new(), None);
+        let mut rest = ops;
+        while let Some(op) = rest.pop() {
+            match op.undo() {
+                Ok(op) => done.push(op),
+                Err(e) => {
+                    rest.push(op);
+                    err = Some(e);
+                    break;
+                }
+            }
+        }
+        // NOTE: the undone part can be redone, and the rest can still be undone
+        done.reverse();
+        if !done.is_empty() {
+            self.redo.push(done);
+        }
+        if !rest.is_empty() {
+            self.undo.push(rest);
+        }
+        match err {
+            Some(err) => Err(format!("Undo failed: {err}")),
+            None => Ok(path),
+        }
+    }
+
+    pub fn redo(&mut self) -> Result<PathBuf, String> {
+        let ops = self.redo.pop().ok_or("Nothing to redo")?;
+        if let Err(err) = ops.iter().try_for_each(|op| op.check_redo()) {
+            self.redo.push(ops);
+            return Err(format!("Can't redo: {err}"));
+        }
+        let path = ops[0].path().to_path_buf();
+        let (mut done, mut err) = (Vec::new(), None);
+        let mut rest = ops.into_iter();
+        for op in rest.by_ref() {
+            match op.redo() {
+                Ok(op) => done.push(op),
+                Err(e) => {
+                    err = Some((op, e));
+                    break;
+                }
+            }
+        }
+        if !done.is_empty() {
+            self.undo.push(done);
+        }
+        match err {
+            Some((op, e)) => {
+                let mut rest: Vec<_> = rest.collect();
+                rest.insert(0, op);
+                self.redo.push(rest);
+                Err(format!("Redo failed: {e}"))
+            }
+            None => Ok(path),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_undo_redo() {
+        let dir = utils::TempDir::new("journal");
+        let (a, b, c) = (dir.join("a"), dir.join("b"), dir.join("c"));
+        fs::write(&a, "a").unwrap();
+        let mut journal = Journal::default();
+
+        fs::rename(&a, &b).unwrap();
+        journal.record(vec![Op::Rename {
+            from: a.clone(),
+            to: b.clone(),
+        }]);
+        fs::create_dir(&c).unwrap();
+        journal.record(vec![Op::Create {
+            path: c.clone(),
+            kind: Created::Dir,
+            stamp: stamp(&c),
+        }]);
+
+        assert_eq!(Ok(c.clone()), journal.undo());
+        assert!(!c.exists());
+        assert_eq!(Ok(a.clone()), journal.undo());
+        assert!(a.exists() && !b.exists());
+        assert!(journal.undo().is_err());
+
+        assert_eq!(Ok(a.clone()), journal.redo());
+        assert!(!a.exists() && b.exists());
+        // NOTE: `c` is changed after undoing its creation, redoing is refused.
+        fs::write(&c, "c").unwrap();
+        assert!(journal.redo().unwrap_err().contains("already exists"));
+        fs::remove_file(&c).unwrap();
+        assert_eq!(Ok(c.clone()), journal.redo());
+        assert!(c.is_dir());
+
+        // NOTE: a new file in `c` changes it, undoing its creation is refused.
+        fs::write(c.join("d"), "d").unwrap();
+        assert!(journal.undo().unwrap_err().contains("changed"));
+        assert!(c.join("d").exists());
+    }
+
+    #[test]
+    fn test_snapshot_renames() {
+        let dir = utils::TempDir::new("snapshot");
+        let (a, b, c) = (dir.join("a"), dir.join("b"), dir.join("c"));
+        fs::write(&a, "a").unwrap();
+        fs::write(&b, "b").unwrap();
+        let snapshot = Snapshot::new(&[a.clone(), b.clone()]);
+        assert!(snapshot.renames().is_empty());
+
+        fs::rename(&a, &c).unwrap();
+        assert_eq!(
+            vec![Op::Rename {
+                from: a.clone(),
+                to: c.clone()
+            }],
+            snapshot.renames()
+        );
+        // NOTE: a new file with the old name isn't a rename
+        fs::write(&a, "a").unwrap();
+        fs::remove_file(&b).unwrap();
+        assert_eq!(1, snapshot.renames().len());
+    }
+}
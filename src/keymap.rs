@@ -0,0 +1,197 @@
+use std::{collections::HashMap, path::Path};
+
+use crate::action::{Action, CallAction, CmdAction, SetAction, CALL_OPS, COMMANDS};
+use crate::config::actions::KEYS;
+use crate::parser;
+use crate::utils;
+
+pub type Keys = HashMap<String, Box<dyn Action>>;
+pub type Commands = HashMap<String, String>; // user-defined commands: name => body
+
+#[derive(Default)]
+pub struct Keymap {
+    pub keys: Keys,
+    pub commands: Commands,
+}
+
+// NOTE: the keymap file is loaded after the default `KEYS` in `src/config.rs`,
+// so it can bind new keys, override or unbind the default ones.
+//
+// # kind key operation, kind is the same as `KEYS`(`c`, `s` or `m`)
+// c J down
+// s zm sortby mtime
+// m gh :cd ~
+// m <Space> $echo "$rust_tfm_f"   # `<Space>` stands for the space key
+// unmap gc                        # remove a binding
+// unmap-all                       # remove all bindings defined before this line
+// cmd home cd ~; set hidden!      # define command `:home`, same as `:cmd home cd ~; set hidden!`
+pub fn load(p: &Path) -> Result<Keymap, String> {
+    let mut keymap = Keymap::default();
+    for t in KEYS {
+        keymap.keys.insert(t.1.to_string(), new_action(t.0, t.2)?);
+    }
+    if !p.exists() {
+        return Ok(keymap);
+    }
+    let content = std::fs::read_to_string(p)
+        .map_err(|err| format!("{err}: read keymap file `{p}` failed", p = p.display()))?;
+    for (i, line) in content.lines().enumerate() {
+        parse_line(&mut keymap, line)
+            .map_err(|err| format!("{p}:{n}: {err}", p = p.display(), n = i + 1))?;
+    }
+    Ok(keymap)
+}
+
+fn parse_line(keymap: &mut Keymap, line: &str) -> Result<(), String> {
+    let keys = &mut keymap.keys;
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') {
+        return Ok(());
+    }
+    let (kind, rest) = split_word(line);
+    let (key, op) = split_word(rest);
+    match kind {
+        "unmap-all" => {
+            if !rest.is_empty() {
+                return Err(format!("`unmap-all` takes no argument, found `{rest}`"));
+            }
+            keys.clear();
+        }
+        "unmap" => {
+            if key.is_empty() || !op.is_empty() {
+                return Err(format!("`unmap` expects exactly one key, found `{rest}`"));
+            }
+            if keys.remove(&parse_key(key)).is_none() {
+                return Err(format!("`{key}` is not mapped"));
+            }
+        }
+        "c" | "s" | "m" => {
+            if key.is_empty() || op.is_empty() {
+                return Err(format!(
+                    "expected `{kind} <key> <operation>`, found `{line}`"
+                ));
+            }
+            keys.insert(parse_key(key), new_action(kind, op)?);
+        }
+        "cmd" => {
+            if key.is_empty() || op.is_empty() {
+                return Err(format!("expected `cmd <name> <body>`, found `{line}`"));
+            }
+            define_cmd(&mut keymap.commands, key, op)?;
+        }
+        _ => {
+            return Err(format!(
+                "unknown kind `{kind}`, expected one of c|s|m|unmap|unmap-all|cmd"
+            ))
+        }
+    }
+    Ok(())
+}
+
+pub fn new_action(kind: &str, op: &str) -> Result<Box<dyn Action>, String> {
+    match kind {
+        "c" if !CALL_OPS.contains(&op) => Err(format!("unknown action `{op}`")),
+        "c" => Ok(Box::new(CallAction::new(op, 1))),
+        "s" => Ok(Box::new(SetAction::new(op)?)),
+        "m" => {
+            let mut chars = op.chars();
+            match chars.next() {
+                Some(prefix @ (':' | '$' | '!' | '&' | '/' | '?')) => {
+                    // NOTE: check the syntax here, but `$VAR` is expanded when running it.
+                    if prefix == ':' {
+                        parser::parse(chars.as_str())?;
+                    }
+                    Ok(Box::new(CmdAction::new(chars.as_str(), prefix)))
+                }
+                _ => Err(format!(
+                    "`{op}` should start with one of the command prefixes `:$!&/?`"
+                )),
+            }
+        }
+        _ => Err(format!("unknown action kind `{kind}`")),
+    }
+}
+
+// A command line with or without prefix, no prefix means a tfm command(`:`).
+pub fn cmd_action(cmd: &str) -> Result<Box<dyn Action>, String> {
+    let cmd = cmd.trim();
+    if cmd.starts_with([':', '$', '!', '&', '/', '?']) {
+        new_action("m", cmd)
+    } else {
+        new_action("m", &format!(":{cmd}"))
+    }
+}
+
+// NOTE: built-in commands can't be redefined.
+pub fn define_cmd(commands: &mut Commands, name: &str, body: &str) -> Result<(), String> {
+    if COMMANDS.contains(&name) {
+        return Err(format!("`{name}` is a built-in command"));
+    }
+    cmd_action(body)?;
+    commands.insert(name.to_string(), body.trim().to_string());
+    Ok(())
+}
+
+pub fn parse_key(key: &str) -> String {
+    if key == "<Space>" {
+        " ".to_string()
+    } else {
+        key.to_string()
+    }
+}
+
+pub fn split_word(s: &str) -> (&str, &str) {
+    match s.find(char::is_whitespace) {
+        Some(i) => (&s[..i], s[i..].trim_start()),
+        None => (s, ""),
+    }
+}
+
+pub fn keymap_file() -> std::path::PathBuf {
+    utils::config_dir().join("keymap")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_line() {
+        let mut keymap = Keymap::default();
+        parse_line(&mut keymap, "c J down").unwrap();
+        parse_line(&mut keymap, "m gh  :cd ~").unwrap();
+        parse_line(&mut keymap, "m <Space> $echo \"a b\"").unwrap();
+        parse_line(&mut keymap, "# comment").unwrap();
+        parse_line(&mut keymap, "cmd home cd ~; set hidden!").unwrap();
+        let keys = &keymap.keys;
+        assert!(keys.contains_key("J"));
+        assert!(keys.contains_key("gh"));
+        assert!(keys.contains_key(" "));
+        assert_eq!(
+            Some("cd ~; set hidden!"),
+            keymap.commands.get("home").map(String::as_str)
+        );
+        parse_line(&mut keymap, "unmap J").unwrap();
+        assert!(!keymap.keys.contains_key("J"));
+        parse_line(&mut keymap, "unmap-all").unwrap();
+        assert!(keymap.keys.is_empty());
+    }
+
+    #[test]
+    fn test_parse_line_error() {
+        let mut keymap = Keymap::default();
+        assert!(parse_line(&mut keymap, "x J down").is_err());
+        assert_eq!(
+            Err("unknown action `dwon`".to_string()),
+            parse_line(&mut keymap, "c J dwon")
+        );
+        // NOTE: the default keys are valid
+        assert!(load(Path::new("/nonexistent")).is_ok());
+        assert!(parse_line(&mut keymap, "c J").is_err());
+        assert!(parse_line(&mut keymap, "m gh cd ~").is_err());
+        assert!(parse_line(&mut keymap, "unmap J").is_err());
+        assert!(parse_line(&mut keymap, "unmap-all now").is_err());
+        assert!(parse_line(&mut keymap, "cmd cd cd ~").is_err());
+        assert!(parse_line(&mut keymap, "cmd home :cd '~").is_err());
+    }
+}
@@ -1,28 +1,138 @@
-use std::env;
-use std::error::Error;
-use std::path::PathBuf;
+use std::{
+    error::Error,
+    io::Write,
+    path::{Path, PathBuf},
+};
 
 mod action;
 mod app;
+mod args;
 mod buffer;
+mod clipboard;
+mod complete;
 mod config;
+mod conflict;
 mod dir;
 mod file;
+mod frecency;
+mod history;
+mod job;
+mod journal;
+mod keymap;
+mod marks;
+mod menu;
 mod nav;
+mod parser;
 mod reg;
+mod remote;
+mod selections;
+mod session;
+mod settings;
+mod theme;
+mod trash;
 mod ui;
 mod utils;
 
-use crate::config::{LOG_FILE_PATH, LOG_LEVEL, RUST_TFM};
+use crate::args::{init_script, Args, USAGE};
+use crate::config::RUST_TFM;
+use crate::session::Session;
+use crate::settings::{settings, Settings};
+use crate::theme::Theme;
+
+fn die(err: &str) -> ! {
+    eprintln!("tfm: {err}");
+    std::process::exit(1);
+}
+
+// NOTE: `-` means stdout
+fn write_paths(p: &Path, paths: &[PathBuf], sep: char) {
+    let content: String = paths
+        .iter()
+        .map(|path| format!("{}{sep}", path.display()))
+        .collect();
+    let res = if p == Path::new("-") {
+        std::io::stdout().write_all(content.as_bytes())
+    } else {
+        std::fs::write(p, content)
+    };
+    if let Err(err) = res {
+        die(&format!("{err}: write to `{p}` failed", p = p.display()));
+    }
+}
 
 fn main() -> Result<(), Box<dyn Error>> {
+    let args = Args::parse(std::env::args().skip(1)).unwrap_or_else(|err| {
+        eprintln!("tfm: {err}\n\n{USAGE}");
+        std::process::exit(2);
+    });
+    if args.help {
+        println!("{USAGE}");
+        return Ok(());
+    }
+    if args.version {
+        println!("tfm {version}", version = env!("CARGO_PKG_VERSION"));
+        return Ok(());
+    }
+    if let Some((ref id, ref cmd)) = args.remote {
+        remote::send(id, cmd).unwrap_or_else(|err| die(&err));
+        return Ok(());
+    }
+    if let Some(ref shell) = args.init {
+        // NOTE: `shell` has been checked by `Args::parse`
+        print!("{}", init_script(shell).unwrap_or_default());
+        return Ok(());
+    }
+    if args.print_default_config {
+        print!("{}", Settings::default());
+        return Ok(());
+    }
+
+    let config_file = args.config.unwrap_or_else(settings::config_file);
+    if !config_file.exists() && config_file != settings::config_file() {
+        die(&format!(
+            "config file `{}` doesn't exist",
+            config_file.display()
+        ));
+    }
+    let mut s = Settings::load(&config_file).unwrap_or_else(|err| die(&err));
+    if let Some(level) = args.log_level {
+        s.log_level = level;
+    }
+    settings::init(s);
+
+    theme::init(Theme::load(&theme::theme_file()).unwrap_or_else(|err| die(&err)));
+
+    let keymap = keymap::load(&keymap::keymap_file()).unwrap_or_else(|err| die(&err));
+
+    let use_session = settings().session || args.restore;
+    let session = if use_session { Session::load() } else { None };
+
+    // NOTE: if `PATH` is a file, start in its parent directory and select it.
+    // Without `PATH`, start in the directory of the restored session.
+    let start_path = match args.path {
+        // NOTE: symbolic links are kept, a link to a file is selected in its own directory.
+        Some(p) => {
+            let path = utils::absolute(&p);
+            if let Err(err) = path.symlink_metadata() {
+                die(&format!("{err}: `{p}`", p = p.display()));
+            }
+            path
+        }
+        None => match session.as_ref().and_then(|s| s.cwd.clone()) {
+            Some(cwd) if cwd.is_dir() => cwd,
+            _ => std::env::current_dir()?,
+        },
+    };
+    let start_dir = if start_path.is_dir() {
+        start_path.as_path()
+    } else {
+        start_path.parent().unwrap_or(&start_path)
+    };
+    std::env::set_current_dir(start_dir)?;
+
     // NOTE: set logfile path
-    if let Some(c) = LOG_FILE_PATH.chars().next() {
-        let log_file_path = if c == '~' {
-            PathBuf::from(format!("{}/{}", env::var("HOME")?, &LOG_FILE_PATH[1..]))
-        } else {
-            PathBuf::from(LOG_FILE_PATH)
-        };
+    if !settings().log_file.is_empty() {
+        let log_file_path = utils::expand_tilde(&settings().log_file);
 
         if let Some(dir) = log_file_path.parent() {
             if !dir.exists() {
@@ -34,8 +144,12 @@ fn main() -> Result<(), Box<dyn Error>> {
             .set_location_level(simplelog::LevelFilter::Error)
             .build();
 
-        simplelog::WriteLogger::init(LOG_LEVEL, config, std::fs::File::create(log_file_path)?)?;
-    } // if `LOG_FILE_PATH` is empty, don't print log info to log file.
+        simplelog::WriteLogger::init(
+            settings().log_level,
+            config,
+            std::fs::File::create(log_file_path)?,
+        )?;
+    } // if `log_file` is empty, don't print log info to log file.
     std::env::set_var("RUST_TFM", RUST_TFM);
     let size = crossterm::terminal::size()?;
     if size.0 < 12 || size.1 < 5 {
@@ -45,9 +159,40 @@ fn main() -> Result<(), Box<dyn Error>> {
             rows = size.1
         );
     } else {
-        let current_dir = std::env::current_dir()?;
-        let mut app = app::App::new();
-        app.run(&current_dir)?;
+        let mut app = app::App::new(keymap);
+        app.choose_files = args.choosefiles.is_some();
+        if let Some(session) = session {
+            app.nav.restore(session);
+        }
+        app.run(&start_path)?;
+        if let Err(err) = app.nav.frecency.save() {
+            log::error!("{err} => save directory history failed");
+        }
+        let session = use_session.then(|| app.nav.session());
+        let last_dir = app.nav.cdir_path();
+        let chosen_files = std::mem::take(&mut app.chosen_files);
+        drop(app); // NOTE: leave alternate screen before printing
+
+        if let Some(ref p) = args.last_dir_path {
+            write_paths(p, std::slice::from_ref(&last_dir), '\n');
+        }
+        if args.print_last_dir {
+            println!("{}", last_dir.display());
+        }
+        let sep = if args.null { '\0' } else { '\n' };
+        if let Some(ref p) = args.choosefiles {
+            if !chosen_files.is_empty() {
+                write_paths(p, &chosen_files, sep);
+            }
+        }
+        if let Some(ref p) = args.choosedir {
+            write_paths(p, &[last_dir], sep);
+        }
+        if let Some(session) = session {
+            if let Err(err) = session.save() {
+                eprintln!("tfm: {err}: save session failed");
+            }
+        }
     }
 
     Ok(())
@@ -0,0 +1,120 @@
+use std::{
+    collections::BTreeMap,
+    fmt::Display,
+    path::{Path, PathBuf},
+};
+
+use crate::utils;
+
+// NOTE: marks shared by all tfm instances, one mark per line: `<char>\t<dir>\t<file under the cursor>`
+//
+// a	/home/user/Downloads	foo.zip
+// c	/home/user/.config	tfm
+pub fn marks_file() -> PathBuf {
+    utils::data_dir().join("marks")
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mark {
+    pub dir: PathBuf,
+    pub file: Option<String>,
+}
+
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Marks(BTreeMap<char, Mark>);
+
+impl Marks {
+    // NOTE: invalid lines are ignored
+    pub fn parse(content: &str) -> Self {
+        let mut marks = BTreeMap::new();
+        for line in content.lines() {
+            let mut fields = line.splitn(3, '\t');
+            let (ch, dir) = match (fields.next(), fields.next()) {
+                (Some(ch), Some(dir)) if ch.chars().count() == 1 && !dir.is_empty() => {
+                    (ch.chars().next().unwrap(), dir)
+                }
+                _ => {
+                    log::warn!("invalid mark `{line}`");
+                    continue;
+                }
+            };
+            let file = fields.next().filter(|f| !f.is_empty()).map(String::from);
+            marks.insert(
+                ch,
+                Mark {
+                    dir: PathBuf::from(dir),
+                    file,
+                },
+            );
+        }
+        Self(marks)
+    }
+
+    pub fn load() -> Self {
+        match std::fs::read_to_string(marks_file()) {
+            Ok(content) => Self::parse(&content),
+            Err(_) => Self::default(),
+        }
+    }
+
+    pub fn save(&self) -> std::io::Result<()> {
+        utils::write_atomic(&marks_file(), &self.to_string())
+    }
+
+    pub fn get(&self, ch: char) -> Option<&Mark> {
+        self.0.get(&ch)
+    }
+
+    pub fn insert(&mut self, ch: char, dir: &Path, file: Option<String>) {
+        self.0.insert(
+            ch,
+            Mark {
+                dir: dir.to_path_buf(),
+                file,
+            },
+        );
+    }
+
+    pub fn remove(&mut self, ch: char) -> bool {
+        self.0.remove(&ch).is_some()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&char, &Mark)> {
+        self.0.iter()
+    }
+}
+
+impl Display for Marks {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (ch, mark) in self.iter() {
+            writeln!(
+                f,
+                "{ch}\t{dir}\t{file}",
+                dir = mark.dir.display(),
+                file = mark.file.as_deref().unwrap_or_default()
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let content = "a\t/tmp\tfoo bar.txt\nbad line\nc\t/home/user/.config\t\n";
+        let marks = Marks::parse(content);
+        assert_eq!(
+            Some(&Mark {
+                dir: PathBuf::from("/tmp"),
+                file: Some("foo bar.txt".to_string())
+            }),
+            marks.get('a')
+        );
+        assert_eq!(None, marks.get('c').unwrap().file);
+        assert_eq!(None, marks.get('b'));
+        assert_eq!(marks, Marks::parse(&marks.to_string()));
+    }
+}
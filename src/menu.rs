@@ -0,0 +1,56 @@
+// What the menu lists, it decides what picking or deleting an entry does.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MenuKind {
+    Marks,
+    Frecency,   // `:zi`
+    Trash,      // `:trash-restore`
+    Jobs,       // `:jobs`
+    Job(usize), // NOTE: the detail of a job
+}
+
+// A list drawn over the panels, like `:marks`.
+pub struct Menu {
+    pub kind: MenuKind,
+    pub title: String,
+    pub entries: Vec<(String, String)>, // (value, text shown in the menu)
+    pub ind: usize,
+}
+
+impl Menu {
+    pub fn new(kind: MenuKind, title: &str, entries: Vec<(String, String)>) -> Self {
+        Self {
+            kind,
+            title: title.to_string(),
+            entries,
+            ind: 0,
+        }
+    }
+
+    pub fn up(&mut self, n: usize) {
+        self.ind = self.ind.saturating_sub(n);
+    }
+
+    pub fn down(&mut self, n: usize) {
+        self.ind = std::cmp::min(self.ind + n, self.entries.len().saturating_sub(1));
+    }
+
+    pub fn top(&mut self) {
+        self.ind = 0;
+    }
+
+    pub fn bottom(&mut self) {
+        self.ind = self.entries.len().saturating_sub(1);
+    }
+
+    // value of the entry under the cursor
+    pub fn value(&self) -> Option<&str> {
+        self.entries.get(self.ind).map(|(value, _)| value.as_str())
+    }
+
+    pub fn remove(&mut self) {
+        if self.ind < self.entries.len() {
+            self.entries.remove(self.ind);
+            self.down(0);
+        }
+    }
+}
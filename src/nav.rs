@@ -1,6 +1,7 @@
 use std::{
-    collections::{HashMap, HashSet},
+    collections::HashMap,
     env,
+    ffi::OsString,
     os::unix::fs::MetadataExt,
     path::{Path, PathBuf},
     sync::{Arc, Mutex},
@@ -8,11 +9,30 @@ use std::{
 };
 use walkdir::WalkDir;
 
-use crate::config::{CASE_INSENSITIVE, HIDDEN, SORT_TYPE};
+use crate::app::Answer;
+use crate::clipboard;
+use crate::complete::{self, Completion};
+use crate::config::{HIDDEN, SORT_TYPE};
+use crate::conflict::{self, Conflict};
 use crate::dir::Dir;
+use crate::frecency::{self, Frecency};
+use crate::history::History;
+use crate::job::{Event, Jobs, Kind, State};
+use crate::journal::{self, Created, Journal, Op};
+use crate::marks::Marks;
+use crate::menu::{Menu, MenuKind};
 use crate::reg::Reg;
+use crate::selections::Selections;
+use crate::session::Session;
+use crate::settings::settings;
+use crate::trash::{self, TrashEntry};
 use crate::utils;
 
+const MAX_JUMPS: usize = 100;
+
+// (directory, name of the file under the cursor)
+pub type Position = (PathBuf, Option<String>);
+
 pub struct Nav {
     pub useful_rows: u16, // terminal rows - 4
 
@@ -25,27 +45,46 @@ pub struct Nav {
     pub reg_preview: Option<Arc<Mutex<Reg>>>,
     pub reg_chan: utils::UnitChan,
 
+    pub count: usize, // pending count typed in normal mode, 0 means no count
     pub cmd_prefix: char,
     pub cmd_string: String,
     pub cmd_string_ind: usize,
     pub search_string: String,
     pub search_direction: bool,
 
-    pub selections: HashSet<PathBuf>,
-    pub cut_or_copy: HashSet<PathBuf>,
+    pub history: History,
+    pub history_ind: Option<usize>, // NOTE: the history entry shown in the command line
+    pub history_draft: String,      // the command line typed before browsing the history
+    pub history_search: Option<String>, // query of `Ctrl-R`
+    pub completion: Option<Completion>,
+    pub frecency: Frecency,
+
+    pub selections: Selections,
+    pub cut_or_copy: Selections,
     pub is_cut: bool,
-    pub mv_cp_total_size: u64,
-    pub mv_cp_size: u64,
-    pub mv_cp_total_chan: utils::U64Chan,
-    pub mv_cp_chan: utils::U64Chan,
+    pub clipboard_chan: utils::UnitChan, // NOTE: the shared clipboard file changed
+    pub jobs: Jobs,                      // NOTE: `paste` and `remove`
+
+    pub menu: Option<Menu>,
+
+    pub jumps_back: Vec<Position>,
+    pub jumps_forward: Vec<Position>,
+    pub prev_dir: Option<Position>, // NOTE: for `cd -`
+
+    // NOTE: cursors restored from the session, used when the directory is loaded for the first time.
+    pub saved_cursors: HashMap<PathBuf, String>,
 
     pub error_message: String,
     pub err_msg_chan: utils::StringChan,
+
+    pub journal: Journal,
+    pub ask_chan: utils::AskChan, // NOTE: questions of `paste` threads
 }
 
 impl Nav {
     pub fn new() -> Self {
         let (_, rows) = utils::terminal_size();
+        let (is_cut, cut_or_copy) = clipboard::load();
         Self {
             useful_rows: rows - 4,
 
@@ -58,25 +97,43 @@ impl Nav {
             reg_preview: None,
             reg_chan: utils::UnitChan::new(),
 
+            count: 0,
             cmd_prefix: char::default(),
             cmd_string: String::new(),
             cmd_string_ind: 0,
             search_string: String::new(),
             search_direction: true,
 
-            selections: HashSet::new(),
-            cut_or_copy: HashSet::new(),
-            is_cut: false,
-            mv_cp_total_size: 0,
-            mv_cp_size: 0,
-            mv_cp_total_chan: utils::U64Chan::new(),
-            mv_cp_chan: utils::U64Chan::new(),
+            history: History::load(),
+            history_ind: None,
+            history_draft: String::new(),
+            history_search: None,
+            completion: None,
+            frecency: Frecency::load(),
+
+            selections: Selections::new(),
+            cut_or_copy,
+            is_cut,
+            clipboard_chan: utils::UnitChan::new(),
+            jobs: Jobs::new(),
+
+            menu: None,
+
+            jumps_back: Vec::new(),
+            jumps_forward: Vec::new(),
+            prev_dir: None,
+
+            saved_cursors: HashMap::new(),
 
             error_message: String::new(),
             err_msg_chan: utils::StringChan::new(),
+
+            journal: Journal::default(),
+            ask_chan: utils::AskChan::new(),
         }
     }
 
+    // NOTE: if `p` is a file, load its parent directory and put the cursor on it.
     pub fn load_dirs(&mut self, p: &Path) -> std::io::Result<()> {
         log::info!("load_dirs starts...");
         let path = if !p.is_absolute() {
@@ -88,6 +145,18 @@ impl Nav {
         } else {
             p.to_path_buf()
         };
+        let (path, sel) = match path.parent() {
+            Some(parent) if !path.is_dir() => (parent.to_path_buf(), Some(path.clone())),
+            _ => (path, None),
+        };
+        self.load_dirs_sel(&path, sel.as_deref());
+        log::info!("load_dirs finished");
+        Ok(())
+    }
+
+    // NOTE: `path` should be an absolute path of a directory, and if `sel`(a file name or path) is
+    // not None, put the cursor on it.
+    fn load_dirs_sel(&mut self, path: &Path, sel: Option<&Path>) {
         let all_paths = path.ancestors().into_iter().collect::<Vec<&Path>>();
         let end = all_paths.len().saturating_sub(1);
         self.dirs = all_paths
@@ -96,10 +165,48 @@ impl Nav {
             .zip(all_paths.as_slice()[..end].iter().rev())
             .map(|(path, name)| self.load_dir(path, Some(name)))
             .collect();
-        let last = self.load_dir(all_paths.first().unwrap(), None);
+        let last = self.load_dir(all_paths.first().unwrap(), sel);
         self.dirs.push(last);
-        log::info!("load_dirs finished");
-        Ok(())
+        self.record_visit(path);
+    }
+
+    // NOTE: the directory history is used by `:z`, and shared by all tfm instances.
+    // It's kept in memory and saved on quit.
+    pub fn record_visit(&mut self, p: &Path) {
+        self.frecency.add(p, frecency::now());
+    }
+
+    // `:z foo bar` goes to the best match, `:zi foo bar` lists the matches to pick.
+    pub fn z(&mut self, fragments: &[String], pick: bool) {
+        let query = fragments.join(" ");
+        let fragments: Vec<_> = fragments.iter().map(String::as_str).collect();
+        let cdir = self.cdir_path();
+        let matches: Vec<_> = self
+            .frecency
+            .query(&fragments, frecency::now())
+            .into_iter()
+            .filter(|(p, _)| *p != cdir && p.is_dir())
+            .collect();
+        if matches.is_empty() {
+            self.error_message = format!("No dir found for `{query}`");
+        } else if pick && matches.len() > 1 {
+            let entries = matches
+                .iter()
+                .map(|(p, score)| {
+                    let p = p.display().to_string();
+                    let text = format!("{score:>8.1}  {p}");
+                    (p, text)
+                })
+                .collect();
+            self.menu = Some(Menu::new(MenuKind::Frecency, "z", entries));
+        } else {
+            let dir = matches[0].0.clone();
+            self.cd_sel(&dir, None);
+        }
+    }
+
+    pub fn forget_dir(&mut self, p: &Path) {
+        self.frecency.remove(p);
     }
 
     pub fn check_dirs(&mut self) {
@@ -125,12 +232,12 @@ impl Nav {
     }
 
     pub fn load_dir(&mut self, p: &Path, name: Option<&Path>) -> Arc<Mutex<Dir>> {
-        let ret = match self.dir_cache.get(p) {
-            Some(value) => value.clone(),
+        let (ret, saved_cursor) = match self.dir_cache.get(p) {
+            Some(value) => (value.clone(), None),
             None => {
                 let value = Arc::new(Mutex::new(Dir::new(p)));
                 self.dir_cache.insert(p.to_path_buf(), value.clone());
-                value
+                (value, self.saved_cursors.remove(p))
             }
         };
         let send_to_thread = ret.clone();
@@ -141,7 +248,7 @@ impl Nav {
             // NOTE: file_name() function will return None, only name ends with `..`, that should not happen.
             name.file_name().unwrap().to_str().map(|n| n.to_string())
         } else {
-            None
+            saved_cursor
         };
         thread::spawn(move || {
             match send_to_thread.lock() {
@@ -224,6 +331,16 @@ impl Nav {
         self.dirs.last().unwrap().clone()
     }
 
+    pub fn cdir_path(&self) -> PathBuf {
+        match self.cdir().lock() {
+            Ok(cdir) => cdir.dir_path.clone(),
+            Err(err) => {
+                log::error!("{err} => cdir_path get lock failed");
+                PathBuf::from("/")
+            }
+        }
+    }
+
     pub fn cfile(&mut self) -> Option<PathBuf> {
         if let Ok(ref cdir) = self.cdir().lock() {
             // NOTE: if files is empty, cdir.files will return None instead of empty slice
@@ -282,25 +399,204 @@ impl Nav {
     }
 
     pub fn cd(&mut self, path_str: &str) {
-        let path = PathBuf::from(path_str);
-        if path.is_dir() {
-            if let Err(err) = self.load_dirs(&path) {
+        if path_str == "-" {
+            match self.prev_dir.clone() {
+                Some((dir, sel)) if dir.is_dir() => self.cd_sel(&dir, sel.as_deref()),
+                Some((dir, _)) => {
+                    self.error_message = format!("No dir found for `{dir}`", dir = dir.display())
+                }
+                None => self.error_message = "No previous dir".to_string(),
+            }
+            return;
+        }
+        // NOTE: relative to the current directory, and symbolic links are kept in the path
+        let path = utils::absolute(&self.cdir_path().join(utils::expand_tilde(path_str)));
+        match path.is_dir() {
+            true => self.cd_sel(&path, None),
+            false => self.error_message = format!("No dir found for `{path_str}`"),
+        }
+    }
+
+    // NOTE: `path` should be an absolute path, and put the cursor on `sel` if it's not None.
+    pub fn cd_sel(&mut self, path: &Path, sel: Option<&str>) {
+        if path != self.cdir_path() {
+            self.push_jump();
+        }
+        self.chdir(path, sel);
+    }
+
+    // Same as `cd_sel`, but doesn't record a jump.
+    fn chdir(&mut self, path: &Path, sel: Option<&str>) {
+        self.load_dirs_sel(path, sel.map(Path::new));
+        if let Err(err) = env::set_current_dir(path) {
+            self.error_message = format!(
+                "ERROR: cd `{path}`:`{err}`",
+                path = path.display(),
+                err = err.kind()
+            );
+        }
+    }
+
+    pub fn position(&mut self) -> Position {
+        let file = self
+            .cfile()
+            .and_then(|f| f.file_name().map(|name| name.to_string_lossy().to_string()));
+        (self.cdir_path(), file)
+    }
+
+    // NOTE: call it before leaving the current directory.
+    pub fn push_jump(&mut self) {
+        let pos = self.position();
+        if self.jumps_back.len() >= MAX_JUMPS {
+            self.jumps_back.remove(0);
+        }
+        self.jumps_back.push(pos.clone());
+        self.jumps_forward.clear();
+        self.prev_dir = Some(pos);
+    }
+
+    // `<C-o>`(back) and `<Tab>`(forward), directories which don't exist any more are skipped.
+    pub fn jump(&mut self, forward: bool) {
+        let pos = self.position();
+        let (from, to) = if forward {
+            (&mut self.jumps_forward, &mut self.jumps_back)
+        } else {
+            (&mut self.jumps_back, &mut self.jumps_forward)
+        };
+        let mut target = None;
+        while let Some(pos) = from.pop() {
+            if pos.0.is_dir() {
+                target = Some(pos);
+                break;
+            }
+        }
+        match target {
+            Some((dir, sel)) => {
+                to.push(pos.clone());
+                self.prev_dir = Some(pos);
+                self.chdir(&dir, sel.as_deref());
+            }
+            None => {
                 self.error_message = format!(
-                    "ERROR: cd load_dirs `{path}`: `{err}`",
-                    path = path.display(),
-                    err = err.kind()
-                );
-                log::error!("{}", self.error_message)
+                    "Already at the {end} of the jump list",
+                    end = if forward { "newest" } else { "oldest" }
+                )
             }
-            if let Err(err) = env::set_current_dir(&path) {
+        }
+    }
+
+    pub fn session(&self) -> Session {
+        let mut cursors: Vec<_> = self
+            .dir_cache
+            .iter()
+            .filter_map(|(p, dir)| {
+                let dir = dir.lock().ok()?;
+                let name = dir.files()?[dir.sp + dir.bp].file_name.clone();
+                Some((p.clone(), name))
+            })
+            .collect();
+        // NOTE: keep the cursors of directories which are not loaded this time.
+        cursors.extend(
+            self.saved_cursors
+                .iter()
+                .map(|(p, name)| (p.clone(), name.clone())),
+        );
+        cursors.sort();
+        Session {
+            cwd: Some(self.cdir_path()),
+            hidden: Some(*HIDDEN.read().unwrap()),
+            sort_type: Some(*SORT_TYPE.read().unwrap()),
+            search: self.search_string.clone(),
+            cursors,
+            selections: self.selections.to_vec(),
+            is_cut: self.is_cut,
+            cut_or_copy: self.cut_or_copy.to_vec(),
+        }
+    }
+
+    // NOTE: paths which don't exist any more are skipped. It should be called before `load_dirs`.
+    pub fn restore(&mut self, session: Session) {
+        let exists = |p: &Path| p.exists() || p.is_symlink();
+        if let Some(hidden) = session.hidden {
+            *HIDDEN.write().unwrap() = hidden;
+        }
+        if let Some(sort_type) = session.sort_type {
+            *SORT_TYPE.write().unwrap() = sort_type;
+        }
+        self.search_string = session.search;
+        self.saved_cursors = session
+            .cursors
+            .into_iter()
+            .filter(|(dir, name)| exists(&dir.join(name)))
+            .collect();
+        for p in session.selections.into_iter().filter(|p| exists(p)) {
+            self.selections.insert(p);
+        }
+        // NOTE: restored only if the shared clipboard file is empty, otherwise it's in use by other
+        // tfm instances, and `paste` would use it anyway.
+        let mut paths = Selections::new();
+        for p in session.cut_or_copy.into_iter().filter(|p| exists(p)) {
+            paths.insert(p);
+        }
+        match clipboard::restore(session.is_cut, &paths) {
+            Ok(true) => (self.is_cut, self.cut_or_copy) = (session.is_cut, paths),
+            Ok(false) => {}
+            Err(err) => {
+                self.error_message = format!("{err}: restore clipboard failed");
+                log::error!("{}", self.error_message);
+            }
+        }
+    }
+
+    pub fn save_mark(&mut self, ch: char) {
+        let (dir, file) = self.position();
+        // NOTE: load before saving, marks may be changed by other tfm instances.
+        let mut marks = Marks::load();
+        marks.insert(ch, &dir, file);
+        if let Err(err) = marks.save() {
+            self.error_message = format!("{err}: save mark `{ch}` failed");
+            log::error!("{}", self.error_message);
+        }
+    }
+
+    pub fn jump_mark(&mut self, ch: char) {
+        match Marks::load().get(ch) {
+            Some(mark) if mark.dir.is_dir() => {
+                let mark = mark.clone();
+                self.cd_sel(&mark.dir, mark.file.as_deref());
+            }
+            Some(mark) => {
                 self.error_message = format!(
-                    "ERROR: cd `{path}`:`{err}`",
-                    path = path.display(),
-                    err = err.kind()
+                    "Mark `{ch}`: `{dir}` doesn't exist",
+                    dir = mark.dir.display()
+                )
+            }
+            None => self.error_message = format!("Mark `{ch}` not set"),
+        }
+    }
+
+    pub fn show_marks(&mut self) {
+        let entries = Marks::load()
+            .iter()
+            .map(|(ch, mark)| {
+                let text = format!(
+                    "{ch}  {dir}  {file}",
+                    dir = mark.dir.display(),
+                    file = mark.file.as_deref().unwrap_or_default()
                 );
+                (ch.to_string(), text)
+            })
+            .collect();
+        self.menu = Some(Menu::new(MenuKind::Marks, "marks", entries));
+    }
+
+    pub fn delete_mark(&mut self, ch: char) {
+        let mut marks = Marks::load();
+        if marks.remove(ch) {
+            if let Err(err) = marks.save() {
+                self.error_message = format!("{err}: delete mark `{ch}` failed");
+                log::error!("{}", self.error_message);
             }
-        } else {
-            self.error_message = format!("No dir found for `{path_str}`");
         }
     }
 
@@ -342,47 +638,134 @@ impl Nav {
         }
     }
 
-    fn cut_or_copy(&mut self) {
+    // NOTE: if selections is empty, `count` files from the cursor are used.
+    fn cut_or_copy(&mut self, count: usize) {
         self.cut_or_copy.clear();
         if self.selections.is_empty() {
             if let Ok(ref cdir) = self.cdir().lock() {
                 if let Some(files) = cdir.files() {
-                    let path = &files[cdir.sp + cdir.bp].file_path;
-                    if path.exists() {
-                        self.cut_or_copy.insert(path.to_owned());
+                    let start = cdir.sp + cdir.bp;
+                    for file in files.iter().skip(start).take(count) {
+                        let path = &file.file_path;
+                        if path.exists() {
+                            self.cut_or_copy.insert(path.to_owned());
+                        }
                     }
                 }
             }
         } else {
             std::mem::swap(&mut self.cut_or_copy, &mut self.selections);
         }
+        self.save_clipboard();
+    }
+
+    fn save_clipboard(&mut self) {
+        if let Err(err) = clipboard::save(self.is_cut, &self.cut_or_copy) {
+            self.error_message = format!("{err}: save clipboard failed");
+            log::error!("{}", self.error_message);
+        }
+    }
+
+    // NOTE: the clipboard file is shared by all tfm instances.
+    pub fn load_clipboard(&mut self) {
+        (self.is_cut, self.cut_or_copy) = clipboard::load();
     }
 
-    pub fn cut(&mut self) {
+    pub fn cut(&mut self, count: usize) {
         self.is_cut = true;
-        self.cut_or_copy();
+        self.cut_or_copy(count);
     }
 
-    pub fn copy(&mut self) {
+    pub fn copy(&mut self, count: usize) {
         self.is_cut = false;
-        self.cut_or_copy();
+        self.cut_or_copy(count);
     }
 
     pub fn clear(&mut self) {
         self.cut_or_copy.clear();
+        self.save_clipboard();
     }
 
-    pub fn paste(&mut self) {
-        if self.mv_cp_total_size != 0 {
-            self.error_message =
-                "ERROR: `paste` or `remove` opration already in progress!!!".to_string();
+    // Pause the running jobs, or resume them if all of them are paused.
+    pub fn pause_jobs(&mut self) {
+        let ids: Vec<_> = self
+            .jobs
+            .list
+            .iter()
+            .filter(|j| j.state.is_active())
+            .map(|j| (j.id, j.state))
+            .collect();
+        if ids.is_empty() {
+            self.error_message = "No `paste` or `remove` in progress".to_string();
             return;
         }
+        let all_paused = ids.iter().all(|(_, state)| *state == State::Paused);
+        for (id, state) in ids {
+            if all_paused || state == State::Running {
+                if let Err(err) = self.jobs.pause(id) {
+                    self.error_message = err;
+                }
+            }
+        }
+        self.refresh_jobs_menu();
+    }
+
+    // NOTE: the file being copied or removed is finished first, the result is reported by the job.
+    pub fn cancel_job(&mut self, id: usize) {
+        if let Err(err) = self.jobs.cancel(id) {
+            self.error_message = err;
+        }
+        self.refresh_jobs_menu();
+    }
+
+    // `:jobs`, the newest job is on the top.
+    pub fn show_jobs(&mut self) {
+        let entries = self
+            .jobs
+            .list
+            .iter()
+            .rev()
+            .map(|j| (j.id.to_string(), j.summary()))
+            .collect();
+        self.menu = Some(Menu::new(MenuKind::Jobs, "jobs", entries));
+    }
+
+    pub fn show_job(&mut self, id: usize) {
+        match self.jobs.get(id) {
+            Some(job) => {
+                let title = format!("job #{id}");
+                self.menu = Some(Menu::new(MenuKind::Job(id), &title, job.details()));
+            }
+            None => self.error_message = format!("No job #{id}"),
+        }
+    }
+
+    // NOTE: the cursor stays at the same line
+    pub fn refresh_jobs_menu(&mut self) {
+        let (kind, ind) = match self.menu {
+            Some(ref menu) => (menu.kind, menu.ind),
+            None => return,
+        };
+        match kind {
+            MenuKind::Jobs => self.show_jobs(),
+            MenuKind::Job(id) if self.jobs.get(id).is_some() => self.show_job(id),
+            _ => return,
+        }
+        if let Some(ref mut menu) = self.menu {
+            menu.ind = ind;
+            menu.down(0);
+        }
+    }
+
+    // NOTE: `policy` decides what to do with files which already exist in the current directory,
+    // see `Conflict`. Pasting a file into its own directory always keeps both.
+    pub fn paste(&mut self, policy: Conflict) {
+        self.load_clipboard();
         if self.cut_or_copy.is_empty() {
             self.error_message = "ERROR: No selected file to paste!!!".to_string();
             return;
         }
-        let path_list = self.cut_or_copy.clone();
+        let path_list = self.cut_or_copy.to_vec();
         let dst_dir = match self.cdir().lock() {
             Ok(lock) => {
                 if lock.readonly {
@@ -397,48 +780,140 @@ impl Nav {
             }
         };
         let is_cut = self.is_cut;
-        let total_chan = self.mv_cp_total_chan.u64_send.clone();
-        let size_chan = self.mv_cp_chan.u64_send.clone();
-        let err_msg_chan = self.err_msg_chan.string_send.clone();
+        let ask_chan = self.ask_chan.ask_send.clone();
+        let kind = if is_cut { Kind::Move } else { Kind::Copy };
+        let worker = self
+            .jobs
+            .add(kind, path_list.clone(), Some(dst_dir.clone()));
+        self.refresh_jobs_menu();
 
         thread::spawn(move || {
-            let mut total_size = 0;
-            let mut update_size = 0;
+            if !worker.wait_start() {
+                worker.finish(Vec::new(), 0, true);
+                return;
+            }
+            let mut ops = Vec::new();
+            let mut overwrite_all = false; // NOTE: `a` is answered
+
+            // NOTE: the worker waits until the question is answered, it's canceled if tfm quits.
+            let ask = |dst: &Path| {
+                let (count, size) = utils::files_summary(&[dst.to_path_buf()]);
+                let msg = format!(
+                    "`{d}` exists({count} files, {size}), overwrite it? [y/n/a]",
+                    d = dst.display(),
+                    size = utils::human_size(size)
+                );
+                let (answer_send, answer_recv) = crossbeam_channel::bounded(1);
+                match ask_chan.send((msg, answer_send)) {
+                    Ok(_) => answer_recv.recv().unwrap_or(Answer::Cancel),
+                    Err(_) => Answer::Cancel,
+                }
+            };
+            let (mut total_files, mut total_size) = (0, 0);
             for p in &path_list {
-                total_size += match utils::dir_size(p) {
-                    Ok(size) => size,
+                let (files, size) = match utils::dir_summary(p) {
+                    Ok(summary) => summary,
                     Err(err) => {
-                        err_msg_chan.send(err).unwrap();
+                        worker.error(err);
+                        worker.finish(Vec::new(), 0, false);
                         return;
                     }
                 };
+                (total_files, total_size) = (total_files + files, total_size + size);
             }
-            total_chan
-                .send(total_size)
-                .map_err(|err| log::error!("{err} => total_chan send failed"))
-                .unwrap();
-            for p in &path_list {
-                let file_name = p.file_name().unwrap().to_str().unwrap().to_string();
-                let mut dst = dst_dir.join(&file_name);
-                let mut count = 1;
-                loop {
-                    if dst.exists() {
-                        let fname = format!("{file_name}.~{count}~");
-                        dst = dst_dir.join(&fname);
-                        count += 1;
-                    } else {
-                        break;
-                    }
+            worker.send(Event::Total(total_files, total_size));
+            let (mut done, mut canceled) = (path_list.len(), false);
+            for (i, p) in path_list.iter().enumerate() {
+                if !worker.keep_going() {
+                    (done, canceled) = (i, true);
+                    break;
                 }
-                if is_cut && std::fs::rename(p, &dst).is_ok() {
-                    update_size += utils::dir_size(&dst).unwrap();
-                    if update_size / 1024 > 4096 {
-                        size_chan.send(update_size).unwrap();
-                        update_size = 0;
+                let file_name = match p.file_name() {
+                    Some(name) => name.to_os_string(),
+                    None => {
+                        worker.error(format!("`{p}` has no file name", p = p.display()));
+                        continue;
+                    }
+                };
+                let mut dst = dst_dir.join(&file_name);
+                let (mut replaced, mut merge, mut newer_only) = (false, false, false);
+                let mut overwritten = None; // NOTE: `dst` is a temporary sibling of it
+                if dst.symlink_metadata().is_ok() {
+                    let action = match if dst == *p { Conflict::Rename } else { policy } {
+                        Conflict::Ask if overwrite_all => Conflict::Overwrite,
+                        Conflict::Ask => match ask(&dst) {
+                            Answer::Yes => Conflict::Overwrite,
+                            Answer::No => Conflict::Rename,
+                            Answer::All => {
+                                overwrite_all = true;
+                                Conflict::Overwrite
+                            }
+                            Answer::Cancel => {
+                                (done, canceled) = (i, true);
+                                break;
+                            }
+                        },
+                        Conflict::OverwriteIfNewer if conflict::is_merge(p, &dst) => {
+                            newer_only = true;
+                            Conflict::Overwrite
+                        }
+                        Conflict::OverwriteIfNewer if conflict::is_newer(p, &dst) => {
+                            Conflict::Overwrite
+                        }
+                        Conflict::OverwriteIfNewer => Conflict::Skip,
+                        action => action,
+                    };
+                    match action {
+                        Conflict::Skip => {
+                            let (files, size) = utils::dir_summary(p).unwrap_or_default();
+                            worker.progress(files, size);
+                            continue;
+                        }
+                        Conflict::Overwrite if p.starts_with(&dst) => {
+                            worker.error(format!(
+                                "`{p}` is inside `{d}`, can't overwrite it",
+                                p = p.display(),
+                                d = dst.display()
+                            ));
+                            let (files, size) = utils::dir_summary(p).unwrap_or_default();
+                            worker.progress(files, size);
+                            continue;
+                        }
+                        Conflict::Overwrite if conflict::is_merge(p, &dst) => {
+                            (replaced, merge) = (true, true)
+                        }
+                        Conflict::Overwrite => {
+                            // NOTE: paste to a temporary sibling first, `dst` is replaced only
+                            // if it succeeds.
+                            let mut tmp = OsString::from(".");
+                            tmp.push(&file_name);
+                            tmp.push(".tfm-tmp");
+                            let tmp = dst_dir.join(tmp);
+                            if let Err(err) = utils::dir_remove(&tmp) {
+                                let err_msg =
+                                    format!("{err}: overwrite `{d}` failed", d = dst.display());
+                                worker.error(err_msg);
+                                continue;
+                            }
+                            overwritten = Some(std::mem::replace(&mut dst, tmp));
+                            replaced = true;
+                        }
+                        _ => {
+                            let mut count = 1;
+                            while dst.symlink_metadata().is_ok() {
+                                let mut fname = file_name.clone();
+                                fname.push(format!(".~{count}~"));
+                                dst = dst_dir.join(&fname);
+                                count += 1;
+                            }
+                        }
                     }
-                    continue;
                 }
-                if p.is_symlink() {
+                worker.file(p);
+                let (mut failed, mut skipped, mut moved) = (false, false, false);
+                if is_cut && !merge && std::fs::rename(p, &dst).is_ok() {
+                    moved = true;
+                } else if p.is_symlink() {
                     match std::fs::read_link(p) {
                         Ok(from) => {
                             if let Err(err) = std::os::unix::fs::symlink(&from, &dst) {
@@ -447,110 +922,281 @@ impl Nav {
                                     f = from.display(),
                                     d = dst.display()
                                 );
-                                log::error!("{err_msg}");
-                                err_msg_chan.send(err_msg).unwrap();
+                                worker.error(err_msg);
+                                failed = true;
                             }
                         }
                         Err(err) => {
                             let err_msg =
                                 format!("{err}: std::fs::read_link `{p}` failed", p = p.display());
-                            log::error!("{err_msg}");
-                            err_msg_chan.send(err_msg).unwrap();
+                            worker.error(err_msg);
+                            failed = true;
                         }
                     }
+                    worker.progress(1, 0);
                 } else {
                     for entry in WalkDir::new(p) {
-                        update_size += match utils::dir_copy(entry.as_ref().unwrap(), &dst, p) {
-                            Ok(size) => size,
+                        // NOTE: the partly copied file is kept, it's removed by hand.
+                        if !worker.keep_going() {
+                            (failed, canceled) = (true, true);
+                            break;
+                        }
+                        let entry = match entry {
+                            Ok(entry) => entry,
                             Err(err) => {
-                                // NOTE: copy this file failed, but continue copy other files.
-                                let err_msg = format!(
-                                    "{err}: copy file `{p}` failed",
-                                    p = entry.unwrap().path().display()
-                                );
-                                log::error!("{err_msg}");
-                                err_msg_chan.send(err_msg).unwrap();
-                                0
+                                let err_msg =
+                                    format!("{err}: read directory `{p}` failed", p = p.display());
+                                worker.error(err_msg);
+                                failed = true;
+                                continue;
                             }
                         };
-                        if update_size / 1024 > 4096 {
-                            size_chan.send(update_size).unwrap();
-                            update_size = 0;
+                        // NOTE: replace conflicting files in the merged directory, except
+                        // directories which are merged too.
+                        let from = entry.path();
+                        worker.file(from);
+                        if merge {
+                            let to = dst.join(from.strip_prefix(p).unwrap());
+                            if to.symlink_metadata().is_ok() && !conflict::is_merge(from, &to) {
+                                if newer_only && !conflict::is_newer(from, &to) {
+                                    worker.progress(
+                                        1,
+                                        from.symlink_metadata().map_or(0, |m| m.len()),
+                                    );
+                                    skipped = true;
+                                    continue;
+                                }
+                                if let Err(err) = utils::dir_remove(&to) {
+                                    let err_msg =
+                                        format!("{err}: overwrite `{t}` failed", t = to.display());
+                                    worker.error(err_msg);
+                                    failed = true;
+                                    continue;
+                                }
+                            }
                         }
+                        let mut progress = |size| worker.progress(0, size);
+                        if let Err(err) = utils::dir_copy(&entry, &dst, p, &mut progress) {
+                            // NOTE: copy this file failed, but continue copy other files.
+                            let err_msg =
+                                format!("{err}: copy file `{p}` failed", p = from.display());
+                            worker.error(err_msg);
+                            failed = true;
+                        }
+                        worker.progress(1, 0);
+                    }
+                }
+                if let Some(target) = overwritten {
+                    let tmp = std::mem::replace(&mut dst, target);
+                    let res = match failed {
+                        true => utils::dir_remove(&tmp),
+                        false => utils::replace_path(&tmp, &dst),
+                    };
+                    if let Err(err) = res {
+                        let err_msg = format!(
+                            "{err}: replace `{d}` with `{t}` failed",
+                            d = dst.display(),
+                            t = tmp.display()
+                        );
+                        worker.error(err_msg);
+                        failed = true;
                     }
                 }
-                if is_cut {
+                // NOTE: a replaced file can't be undone safely, as below
+                if moved {
+                    if !failed && !replaced {
+                        ops.push(Op::Rename {
+                            from: p.clone(),
+                            to: dst.clone(),
+                        });
+                    }
+                    let (files, size) = utils::dir_summary(&dst).unwrap_or_default();
+                    worker.progress(files, size);
+                    continue;
+                }
+                // NOTE: the source is kept if some of its files aren't moved
+                if is_cut && (failed || skipped) {
+                    worker.error(format!(
+                        "`{p}` isn't removed, some files aren't moved",
+                        p = p.display()
+                    ));
+                } else if is_cut {
                     if let Err(err) = utils::dir_remove(p) {
-                        err_msg_chan
-                            .send(format!("{err}: Remove file `{p}` failed", p = p.display()))
-                            .unwrap();
+                        worker.error(format!("{err}: Remove file `{p}` failed", p = p.display()));
                         break;
                     }
                 }
+                // NOTE: a partly copied file or a replaced file can't be undone safely
+                if !failed && !replaced {
+                    ops.push(if is_cut {
+                        Op::Rename {
+                            from: p.clone(),
+                            to: dst.clone(),
+                        }
+                    } else {
+                        Op::Copy {
+                            from: p.clone(),
+                            stamp: journal::stamp(&dst),
+                            to: dst,
+                        }
+                    });
+                }
+                if canceled {
+                    done = i;
+                    break;
+                }
             }
-            total_chan
-                .send(0)
-                .map_err(|err| log::error!("{err} => total_chan send failed"))
-                .unwrap();
+            worker.finish(ops, done, canceled);
         });
 
         if self.is_cut {
             self.cut_or_copy.clear();
+            self.save_clipboard();
         }
     }
 
-    pub fn remove(&mut self) {
-        if self.mv_cp_total_size != 0 {
-            self.error_message =
-                "ERROR: `paste` or `remove` opration already in progress!!!".to_string();
+    // NOTE: the sources of a cut which aren't moved(canceled, skipped or failed) are put back to
+    // the clipboard, unless something else has been cut or copied since then.
+    pub fn keep_unpasted(&mut self, sources: &[PathBuf]) {
+        self.load_clipboard();
+        let left: Vec<_> = sources
+            .iter()
+            .filter(|p| p.symlink_metadata().is_ok())
+            .collect();
+        if !self.cut_or_copy.is_empty() || left.is_empty() {
             return;
         }
+        self.is_cut = true;
+        for p in left {
+            self.cut_or_copy.insert(p.clone());
+        }
+        self.save_clipboard();
+    }
 
+    pub fn remove(&mut self) {
         if self.selections.is_empty() {
             self.error_message = "No selected file to remove".to_string();
             return;
         }
-        let path_list = self.selections.clone();
-        let total_chan = self.mv_cp_total_chan.u64_send.clone();
-        let size_chan = self.mv_cp_chan.u64_send.clone();
-        let err_msg_chan = self.err_msg_chan.string_send.clone();
+        let path_list = self.selections.to_vec();
+        let worker = self.jobs.add(Kind::Remove, path_list.clone(), None);
+        self.refresh_jobs_menu();
         thread::spawn(move || {
-            let mut total_size = 0;
-            let mut update_size = 0;
+            if !worker.wait_start() {
+                worker.finish(Vec::new(), 0, true);
+                return;
+            }
+            let (mut total_files, mut total_size) = (0, 0);
             for p in &path_list {
-                total_size += match utils::dir_size(p) {
-                    Ok(size) => size,
+                let (files, size) = match utils::dir_summary(p) {
+                    Ok(summary) => summary,
                     Err(err) => {
-                        err_msg_chan.send(err).unwrap();
+                        worker.error(err);
+                        worker.finish(Vec::new(), 0, false);
                         return;
                     }
                 };
+                (total_files, total_size) = (total_files + files, total_size + size);
             }
-            total_chan
-                .send(total_size)
-                .map_err(|err| log::error!("{err} => total_chan send failed"))
-                .unwrap();
-            for p in &path_list {
-                update_size += utils::dir_size(p).unwrap();
-                if let Err(err) = utils::dir_remove(p) {
-                    err_msg_chan
-                        .send(format!("{err}: Remove file `{p}` failed", p = p.display()))
-                        .unwrap();
+            worker.send(Event::Total(total_files, total_size));
+            let (mut done, mut canceled) = (path_list.len(), false);
+            for (i, p) in path_list.iter().enumerate() {
+                if !worker.keep_going() {
+                    (done, canceled) = (i, true);
                     break;
                 }
-                if update_size / 1024 > 4096 {
-                    size_chan.send(update_size).unwrap();
-                    update_size = 0;
+                worker.file(p);
+                let (files, size) = utils::dir_summary(p).unwrap_or_default();
+                if let Err(err) = utils::dir_remove(p) {
+                    worker.error(format!("{err}: Remove file `{p}` failed", p = p.display()));
+                    done = i;
+                    break;
                 }
+                worker.progress(files, size);
             }
-            total_chan
-                .send(0)
-                .map_err(|err| log::error!("{err} => total_chan send failed"))
-                .unwrap();
+            worker.finish(Vec::new(), done, canceled);
         });
         self.selections.clear();
     }
 
+    // Move the selected files(or the file under the cursor) to the trash.
+    pub fn trash(&mut self) {
+        let paths = if self.selections.is_empty() {
+            self.cfile().into_iter().collect()
+        } else {
+            self.selections.to_vec()
+        };
+        if paths.is_empty() {
+            self.error_message = "No file to trash".to_string();
+            return;
+        }
+        let mut ops = Vec::new();
+        for p in &paths {
+            match trash::put(p) {
+                Ok(entry) => ops.push(Op::Trash(entry)),
+                Err(err) => {
+                    self.error_message = err;
+                    log::error!("{err_msg}", err_msg = self.error_message);
+                    break;
+                }
+            }
+        }
+        self.journal.record(ops);
+        self.selections.clear();
+        let rows = self.useful_rows as usize;
+        if let Ok(mut cdir) = self.cdir().lock() {
+            cdir.update();
+            cdir.bound_position(rows);
+        }
+    }
+
+    // `:trash-restore`, entries are the `.trashinfo` files, the newest first.
+    pub fn show_trash(&mut self) {
+        let entries = trash::list()
+            .into_iter()
+            .map(|e| {
+                let text = format!(
+                    "{date}  {path}",
+                    date = e.deletion_date.replace('T', " "),
+                    path = e.path.display()
+                );
+                (e.info.display().to_string(), text)
+            })
+            .collect();
+        self.menu = Some(Menu::new(MenuKind::Trash, "trash", entries));
+    }
+
+    pub fn find_trash(&mut self, info: &Path) -> Option<TrashEntry> {
+        let entry = trash::list().into_iter().find(|e| e.info == info);
+        if entry.is_none() {
+            self.error_message = format!("`{}` isn't in the trash any more", info.display());
+        }
+        entry
+    }
+
+    // NOTE: put the cursor on the restored file if it's in the current directory
+    pub fn restore_trash(&mut self, info: &Path) {
+        if let Some(entry) = self.find_trash(info) {
+            match trash::restore(&entry) {
+                Ok(_) => self.sel_new(&entry.path),
+                Err(err) => {
+                    self.error_message = err;
+                    log::error!("{err_msg}", err_msg = self.error_message);
+                }
+            }
+        }
+    }
+
+    pub fn delete_trash(&mut self, entries: &[TrashEntry]) {
+        for entry in entries {
+            if let Err(err) = trash::delete(entry) {
+                self.error_message = err;
+                log::error!("{err_msg}", err_msg = self.error_message);
+                break;
+            }
+        }
+    }
+
     pub fn rename(&mut self, new_name: &str) {
         match self.cdir().lock() {
             Ok(mut lock) => match lock.files() {
@@ -574,6 +1220,10 @@ impl Nav {
                         }
                         lock.update();
                         lock.sel(new_name, self.useful_rows as usize);
+                        self.journal.record(vec![Op::Rename {
+                            from: path,
+                            to: new_path,
+                        }]);
                     }
                 }
                 None => {
@@ -588,6 +1238,138 @@ impl Nav {
         }
     }
 
+    // `:mkdir`, parent directories are created as needed.
+    pub fn mkdir(&mut self, path: &str) {
+        let abs = utils::absolute(Path::new(path));
+        let mut new_dirs: Vec<_> = abs.ancestors().take_while(|d| !d.exists()).collect();
+        new_dirs.reverse();
+        match std::fs::create_dir_all(path) {
+            Ok(_) => {
+                let ops = new_dirs
+                    .into_iter()
+                    .map(|d| Op::Create {
+                        path: d.to_path_buf(),
+                        kind: Created::Dir,
+                        stamp: journal::stamp(d),
+                    })
+                    .collect();
+                self.journal.record(ops);
+                self.sel_new(Path::new(path));
+            }
+            Err(err) => self.create_failed(err, format!("mkdir `{path}`")),
+        }
+    }
+
+    // `:touch`, create the file or update its modification time.
+    pub fn touch(&mut self, path: &str) {
+        let existed = Path::new(path).exists();
+        let res = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .and_then(|f| f.set_modified(std::time::SystemTime::now()));
+        match res {
+            Ok(_) => {
+                if !existed {
+                    self.record_create(Path::new(path), Created::File);
+                }
+                self.sel_new(Path::new(path));
+            }
+            Err(err) => self.create_failed(err, format!("touch `{path}`")),
+        }
+    }
+
+    // `:ln [-s] <target> [<link>]`, `link` defaults to the file name of `target` in the current directory.
+    pub fn link(&mut self, target: &str, link: Option<&str>, symbolic: bool) {
+        let link = match link.map(PathBuf::from) {
+            Some(link) => link,
+            None => match Path::new(target).file_name() {
+                Some(name) => PathBuf::from(name),
+                None => {
+                    self.error_message = format!("`ln`: no file name in `{target}`");
+                    return;
+                }
+            },
+        };
+        let res = if symbolic {
+            std::os::unix::fs::symlink(target, &link)
+        } else {
+            std::fs::hard_link(target, &link)
+        };
+        match res {
+            Ok(_) => {
+                let kind = if symbolic {
+                    Created::Symlink(PathBuf::from(target))
+                } else {
+                    Created::HardLink(utils::absolute(Path::new(target)))
+                };
+                self.record_create(&link, kind);
+                self.sel_new(&link);
+            }
+            Err(err) => self.create_failed(
+                err,
+                format!("ln `{target}` to `{link}`", link = link.display()),
+            ),
+        }
+    }
+
+    fn record_create(&mut self, p: &Path, kind: Created) {
+        let path = utils::absolute(p);
+        let stamp = journal::stamp(&path);
+        self.journal.record(vec![Op::Create { path, kind, stamp }]);
+    }
+
+    pub fn undo(&mut self, redo: bool) {
+        let res = if redo {
+            self.journal.redo()
+        } else {
+            self.journal.undo()
+        };
+        match res {
+            Ok(path) => self.sel_new(&path),
+            Err(err) => {
+                self.error_message = err;
+                log::error!("{err_msg}", err_msg = self.error_message);
+            }
+        }
+    }
+
+    fn create_failed(&mut self, err: std::io::Error, what: String) {
+        self.error_message = format!("{err}: {what} failed");
+        log::error!("{err_msg}", err_msg = self.error_message);
+    }
+
+    // NOTE: refresh the current directory, and put the cursor on the entry containing the new file
+    // `p` if it's inside the current directory(`a` of `a/b/c`).
+    fn sel_new(&mut self, p: &Path) {
+        let (parent, name) = match (p.parent(), p.file_name()) {
+            (Some(parent), Some(name)) => (parent, name),
+            _ => return,
+        };
+        let parent = if parent.as_os_str().is_empty() {
+            Path::new(".")
+        } else {
+            parent
+        };
+        let path = match parent.canonicalize() {
+            Ok(parent) => parent.join(name),
+            Err(_) => return,
+        };
+        let rows = self.useful_rows as usize;
+        if let Ok(mut cdir) = self.cdir().lock() {
+            cdir.update();
+            let entry = path
+                .strip_prefix(&cdir.dir_path)
+                .ok()
+                .and_then(|rel| rel.iter().next())
+                .map(|name| name.to_string_lossy().to_string());
+            match entry {
+                Some(name) => cdir.sel(&name, rows),
+                None => cdir.bound_position(rows),
+            }
+        }
+    }
+
     pub fn export_files(&mut self) {
         let currfile = match self.cfile() {
             Some(curr) => format!("{}", curr.display()),
@@ -663,6 +1445,122 @@ impl Nav {
         };
     }
 
+    // NOTE: `ind` is indexed from 0
+    pub fn goto(&mut self, ind: usize) {
+        if let Ok(ref mut cdir) = self.cdir().try_lock() {
+            cdir.goto(ind, self.useful_rows as usize);
+        };
+    }
+
+    // NOTE: reload the history before adding, it's shared by all tfm instances.
+    pub fn add_history(&mut self) {
+        let mut history = History::load();
+        history.add(self.cmd_prefix, &self.cmd_string, settings().history_size);
+        if let Err(err) = history.save() {
+            log::error!("{err} => save command line history failed");
+        }
+        self.history = history;
+    }
+
+    pub fn reset_history(&mut self) {
+        self.history_ind = None;
+        self.history_draft.clear();
+        self.history_search = None;
+    }
+
+    fn show_history(&mut self, ind: Option<usize>) {
+        if self.history_ind.is_none() {
+            self.history_draft = self.cmd_string.clone();
+        }
+        self.history_ind = ind;
+        self.cmd_string = match ind {
+            Some(i) => self.history.get(self.cmd_prefix)[i].clone(),
+            None => std::mem::take(&mut self.history_draft),
+        };
+        self.cmd_string_ind = self.cmd_string.len();
+    }
+
+    // `Up`(older) and `Down`(newer) in the command line
+    pub fn history_step(&mut self, older: bool) {
+        let len = self.history.get(self.cmd_prefix).len();
+        let ind = match (self.history_ind, older) {
+            (None, true) if len > 0 => Some(len - 1),
+            (Some(i), true) => Some(i.saturating_sub(1)),
+            (Some(i), false) if i + 1 < len => Some(i + 1),
+            (Some(_), false) => None,
+            (None, _) => return,
+        };
+        self.show_history(ind);
+    }
+
+    // `Ctrl-R`, show the newest command containing the query and older than the current one,
+    // or the current one if `again` is false.
+    pub fn history_search(&mut self, again: bool) {
+        let query = self.history_search.clone().unwrap_or_default();
+        let before = match self.history_ind {
+            Some(i) if again => i,
+            Some(i) => i + 1,
+            None => self.history.get(self.cmd_prefix).len(),
+        };
+        if let Some(i) = self.history.search(self.cmd_prefix, &query, before) {
+            self.show_history(Some(i));
+        }
+    }
+
+    // NOTE: restore the command line typed before `Ctrl-R`
+    pub fn cancel_history_search(&mut self) {
+        if self.history_ind.is_some() {
+            self.show_history(None);
+        }
+        self.history_search = None;
+    }
+
+    // `Tab`(forward) and `Shift-Tab` in the command line, the first one completes the word before
+    // the cursor as much as possible, and the following ones cycle through the candidates.
+    pub fn complete(&mut self, user_cmds: &[&str], forward: bool) {
+        if let Some(ref mut c) = self.completion {
+            let len = c.candidates.len();
+            let ind = match (c.ind, forward) {
+                (None, true) => 0,
+                (None, false) => len - 1,
+                (Some(i), true) => (i + 1) % len,
+                (Some(i), false) => (i + len - 1) % len,
+            };
+            c.ind = Some(ind);
+            let (start, text) = (c.start, c.candidates[ind].clone());
+            self.replace_cmd_word(start, &text);
+            return;
+        }
+        let line = &self.cmd_string[..self.cmd_string_ind];
+        let (start, candidates) = complete::candidates(self.cmd_prefix, line, user_cmds);
+        match candidates.len() {
+            0 => {}
+            1 => {
+                let mut text = candidates[0].clone();
+                if !text.ends_with('/') {
+                    text.push(' ');
+                }
+                self.replace_cmd_word(start, &text);
+            }
+            _ => {
+                let text = complete::common_prefix(&candidates).to_string();
+                self.replace_cmd_word(start, &text);
+                self.completion = Some(Completion {
+                    start,
+                    candidates,
+                    ind: None,
+                });
+            }
+        }
+    }
+
+    // NOTE: replace the command line from `start` to the cursor with `text`
+    fn replace_cmd_word(&mut self, start: usize, text: &str) {
+        self.cmd_string
+            .replace_range(start..self.cmd_string_ind, text);
+        self.cmd_string_ind = start + text.len();
+    }
+
     pub fn search(&mut self, rev: bool) {
         if !self.cmd_string.is_empty() {
             self.search_string = self.cmd_string.clone();
@@ -693,7 +1591,7 @@ impl Nav {
         if let Ok(ref mut cdir) = self.cdir().lock() {
             let files_len = cdir.files_len;
             let cpos = cdir.sp + cdir.bp;
-            let ss = if CASE_INSENSITIVE {
+            let ss = if settings().case_insensitive {
                 self.search_string.to_ascii_lowercase()
             } else {
                 self.search_string.clone()
@@ -701,7 +1599,7 @@ impl Nav {
             if let Some(files) = cdir.files() {
                 let mut pos = if cpos == files_len - 1 { 0 } else { cpos + 1 };
                 while pos != cpos {
-                    let file_name = if CASE_INSENSITIVE {
+                    let file_name = if settings().case_insensitive {
                         files[pos].file_name.to_ascii_lowercase()
                     } else {
                         files[pos].file_name.clone()
@@ -728,7 +1626,7 @@ impl Nav {
         if let Ok(ref mut cdir) = self.cdir().lock() {
             let files_len = cdir.files_len;
             let cpos = cdir.sp + cdir.bp;
-            let ss = if CASE_INSENSITIVE {
+            let ss = if settings().case_insensitive {
                 self.search_string.to_ascii_lowercase()
             } else {
                 self.search_string.clone()
@@ -736,7 +1634,7 @@ impl Nav {
             if let Some(files) = cdir.files() {
                 let mut pos = if cpos != 0 { cpos } else { files_len } - 1;
                 while pos != cpos {
-                    let file_name = if CASE_INSENSITIVE {
+                    let file_name = if settings().case_insensitive {
                         files[pos].file_name.to_ascii_lowercase()
                     } else {
                         files[pos].file_name.clone()
@@ -0,0 +1,202 @@
+use std::{env, iter::Peekable, str::Chars};
+
+use crate::utils;
+
+// Split a tfm command line(like `:set hidden!; cd ~/src`, without the prefix) into commands and words.
+//
+// - `;` separates commands, and blanks separate words
+// - '...' keeps everything literally
+// - "..." expands `$VAR`, and `\` only escapes `"`, `\` and `$` in it
+// - `\` escapes the next character outside quotes
+// - `$VAR` and `${VAR}` are expanded outside single quotes, an unset variable is empty
+// - `~` at the start of a word is expanded to `$HOME`
+pub fn parse(s: &str) -> Result<Vec<Vec<String>>, String> {
+    let mut cmds = Vec::new();
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let mut in_word = false; // NOTE: `''` is an empty word
+    let mut chars = s.chars().peekable();
+    while let Some(ch) = chars.next() {
+        match ch {
+            ' ' | '\t' | ';' => {
+                if in_word {
+                    words.push(std::mem::take(&mut word));
+                    in_word = false;
+                }
+                if ch == ';' && !words.is_empty() {
+                    cmds.push(std::mem::take(&mut words));
+                }
+            }
+            '\\' => {
+                in_word = true;
+                match chars.next() {
+                    Some(ch) => word.push(ch),
+                    None => return Err("trailing backslash".to_string()),
+                }
+            }
+            '\'' => {
+                in_word = true;
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(ch) => word.push(ch),
+                        None => return Err("unterminated single quote".to_string()),
+                    }
+                }
+            }
+            '"' => {
+                in_word = true;
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.peek() {
+                            Some(&ch @ ('"' | '\\' | '$')) => {
+                                word.push(ch);
+                                chars.next();
+                            }
+                            _ => word.push('\\'),
+                        },
+                        Some('$') => expand_var(&mut chars, &mut word)?,
+                        Some(ch) => word.push(ch),
+                        None => return Err("unterminated double quote".to_string()),
+                    }
+                }
+            }
+            '$' => {
+                in_word = true;
+                expand_var(&mut chars, &mut word)?;
+            }
+            '~' if !in_word && matches!(chars.peek(), None | Some('/' | ' ' | '\t' | ';')) => {
+                in_word = true;
+                word += &utils::expand_tilde("~").to_string_lossy();
+            }
+            _ => {
+                in_word = true;
+                word.push(ch);
+            }
+        }
+    }
+    if in_word {
+        words.push(word);
+    }
+    if !words.is_empty() {
+        cmds.push(words);
+    }
+    Ok(cmds)
+}
+
+// NOTE: `chars` is right after the `$`
+fn expand_var(chars: &mut Peekable<Chars>, word: &mut String) -> Result<(), String> {
+    let mut name = String::new();
+    if chars.peek() == Some(&'{') {
+        chars.next();
+        loop {
+            match chars.next() {
+                Some('}') => break,
+                Some(ch) => name.push(ch),
+                None => return Err("unterminated `${`".to_string()),
+            }
+        }
+    } else {
+        while let Some(&ch) = chars.peek() {
+            if !(ch.is_ascii_alphanumeric() || ch == '_') {
+                break;
+            }
+            name.push(ch);
+            chars.next();
+        }
+    }
+    if name.is_empty() {
+        word.push('$');
+    } else {
+        *word += &env::var(&name).unwrap_or_default();
+    }
+    Ok(())
+}
+
+// Quote `s` so that `parse` gives it back as one word.
+pub fn quote(s: &str) -> String {
+    if !s.is_empty()
+        && s.chars()
+            .all(|ch| ch.is_alphanumeric() || "-_./,+=@%:".contains(ch))
+    {
+        s.to_string()
+    } else {
+        format!("'{}'", s.replace('\'', "'\\''"))
+    }
+}
+
+// The body of `:cmd` and `:map` is the rest of the command line taken as is, like in the keymap
+// file, so quotes, `;` and `$VAR` are kept until it runs. A body quoted as one word is unquoted.
+pub fn definition_body(s: &str) -> Result<String, String> {
+    match &parse(s)?[..] {
+        [words] if words.len() == 1 => Ok(words[0].clone()),
+        _ => Ok(s.trim().to_string()),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn words(s: &str) -> Vec<Vec<String>> {
+        parse(s).unwrap()
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            vec![vec!["set", "hidden!"], vec!["cd", "/tmp/a b"]],
+            words("set hidden!; cd '/tmp/a b'")
+        );
+        assert_eq!(
+            vec![vec!["rename", "a \"b\" c", "", "d;e"]],
+            words(r#"rename "a \"b\" c" '' d\;e"#)
+        );
+        assert_eq!(vec![vec!["a\\b", "$", "x"]], words(r#""a\b" $ x;;"#));
+        assert!(words(" ; ").is_empty());
+
+        env::set_var("TFM_PARSER_TEST", "v a");
+        let home = env::var("HOME").unwrap_or_default();
+        assert_eq!(
+            vec![vec![
+                "v a".to_string(),
+                "v a-v a".to_string(),
+                "$TFM_PARSER_TEST".to_string(),
+                format!("{home}/src"),
+                "a~".to_string(),
+                "~".to_string(),
+            ]],
+            words(
+                r#"$TFM_PARSER_TEST "$TFM_PARSER_TEST-${TFM_PARSER_TEST}" '$TFM_PARSER_TEST' ~/src a~ '~'"#
+            )
+        );
+    }
+
+    #[test]
+    fn test_parse_error() {
+        assert!(parse("cd 'abc").is_err());
+        assert!(parse("cd \"abc").is_err());
+        assert!(parse("cd abc\\").is_err());
+        assert!(parse("cd ${HOME").is_err());
+    }
+
+    #[test]
+    fn test_quote() {
+        for s in ["abc.txt", "a b", "it's", "", "$HOME", "~", "a;b"] {
+            assert_eq!(vec![vec![s.to_string()]], words(&quote(s)));
+        }
+    }
+
+    #[test]
+    fn test_definition_body() {
+        let body = |s| definition_body(s).unwrap();
+        assert_eq!(r#"$mkdir "$1""#, body(r#" '$mkdir "$1"'"#));
+        assert_eq!(
+            r#"$$EDITOR "a b" 'it''s' $HOME"#,
+            body(r#"$$EDITOR "a b" 'it''s' $HOME "#)
+        );
+        assert_eq!("cd ~; set hidden!", body("cd ~; set hidden!"));
+        assert!(definition_body("'a b").is_err());
+    }
+}
@@ -3,7 +3,7 @@ use std::{
     time::SystemTime,
 };
 
-use crate::config::PREVIEWER;
+use crate::settings::settings;
 
 pub struct Reg {
     pub path: PathBuf,
@@ -36,7 +36,7 @@ impl Reg {
         // of preview pane respectively.
         cmd.arg("-c").arg(format!(
             "{} '{}' '{}' '{}' '{}' '{}'",
-            PREVIEWER,
+            settings().previewer,
             self.path.display(),
             layout.0,
             layout.1,
@@ -54,7 +54,7 @@ impl Reg {
                         }
                         Err(err) => log::error!(
                             "{err} => reg_preview convert command `{cmd} {file}` output failed!",
-                            cmd = PREVIEWER,
+                            cmd = settings().previewer,
                             file = self.path.display()
                         ),
                     }
@@ -62,7 +62,7 @@ impl Reg {
             }
             Err(err) => log::error!(
                 "{err} => reg_preview run command `{cmd} {file}` failed!",
-                cmd = PREVIEWER,
+                cmd = settings().previewer,
                 file = self.path.display()
             ),
         }
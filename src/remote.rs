@@ -0,0 +1,150 @@
+use crossbeam_channel::Sender;
+use std::{
+    env,
+    io::{BufRead, BufReader, Write},
+    os::unix::{
+        fs::{DirBuilderExt, MetadataExt},
+        net::{UnixListener, UnixStream},
+    },
+    path::{Path, PathBuf},
+    thread,
+};
+
+// `$XDG_RUNTIME_DIR/tfm` or `/tmp/tfm-$UID`
+fn socket_dir() -> PathBuf {
+    match env::var("XDG_RUNTIME_DIR") {
+        Ok(dir) if !dir.is_empty() => PathBuf::from(dir).join("tfm"),
+        _ => PathBuf::from(format!("/tmp/tfm-{uid}", uid = users::get_current_uid())),
+    }
+}
+
+// NOTE: the socket runs shell commands, so its directory must be a real directory which only the
+// current user can access, not one created by someone else in `/tmp`.
+fn check_dir(dir: &Path) -> std::io::Result<()> {
+    let metadata = dir.symlink_metadata()?;
+    let err_msg = if !metadata.is_dir() {
+        "isn't a directory"
+    } else if metadata.uid() != users::get_current_uid() {
+        "isn't owned by the current user"
+    } else if metadata.mode() & 0o077 != 0 {
+        "is accessible by other users"
+    } else {
+        return Ok(());
+    };
+    Err(std::io::Error::other(format!(
+        "socket directory `{d}` {err_msg}",
+        d = dir.display()
+    )))
+}
+
+pub fn socket_path(id: &str) -> PathBuf {
+    socket_dir().join(format!("{id}.sock"))
+}
+
+// Every line received from the socket is a command(like `:cd /tmp` or `$mkdir foo`),
+// and it will be sent to `cmd_send`.
+pub struct Server {
+    pub id: String,
+    path: PathBuf,
+}
+
+impl Server {
+    pub fn start(cmd_send: Sender<String>) -> std::io::Result<Self> {
+        let id = std::process::id().to_string();
+        let dir = socket_dir();
+        if dir.symlink_metadata().is_err() {
+            std::fs::DirBuilder::new()
+                .recursive(true)
+                .mode(0o700)
+                .create(&dir)?;
+        }
+        check_dir(&dir)?;
+        let path = socket_path(&id);
+        // NOTE: a socket file left by a crashed tfm with the same pid
+        if path.exists() {
+            std::fs::remove_file(&path)?;
+        }
+        let listener = UnixListener::bind(&path)?;
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let stream = match stream {
+                    Ok(stream) => stream,
+                    Err(err) => {
+                        log::error!("{err} => remote accept connection failed");
+                        continue;
+                    }
+                };
+                // NOTE: a client which never closes the connection doesn't block the others
+                let cmd_send = cmd_send.clone();
+                thread::spawn(move || serve(stream, cmd_send));
+            }
+        });
+        Ok(Self { id, path })
+    }
+}
+
+fn serve(stream: UnixStream, cmd_send: Sender<String>) {
+    for line in BufReader::new(stream).lines() {
+        match line {
+            Ok(line) if line.trim().is_empty() => {}
+            Ok(line) => {
+                log::info!("remote received `{line}`");
+                if let Err(err) = cmd_send.send(line) {
+                    log::error!("{err} => remote cmd_send send failed");
+                    return;
+                }
+            }
+            Err(err) => {
+                log::error!("{err} => remote read line failed");
+                return;
+            }
+        }
+    }
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        if let Err(err) = std::fs::remove_file(&self.path) {
+            log::error!(
+                "{err} => remove socket `{p}` failed",
+                p = self.path.display()
+            );
+        }
+    }
+}
+
+// Client side of `tfm --remote <id> <cmd>`
+pub fn send(id: &str, cmd: &str) -> Result<(), String> {
+    if cmd.contains('\n') {
+        return Err(format!("command `{cmd}` contains newline"));
+    }
+    let path = socket_path(id);
+    let mut stream = UnixStream::connect(&path).map_err(|err| {
+        format!(
+            "{err}: connect to tfm instance `{id}`(`{p}`) failed",
+            p = path.display()
+        )
+    })?;
+    stream
+        .write_all(format!("{cmd}\n").as_bytes())
+        .map_err(|err| format!("{err}: send `{cmd}` to tfm instance `{id}` failed"))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::os::unix::fs::PermissionsExt;
+
+    #[test]
+    fn test_check_dir() {
+        let tmp = crate::utils::TempDir::new("remote");
+        let dir = tmp.join("sock");
+        std::fs::DirBuilder::new().mode(0o700).create(&dir).unwrap();
+        assert!(check_dir(&dir).is_ok());
+        let link = tmp.join("link");
+        std::os::unix::fs::symlink(&dir, &link).unwrap();
+        assert!(check_dir(&link).is_err());
+        std::fs::set_permissions(&dir, std::fs::Permissions::from_mode(0o777)).unwrap();
+        assert!(check_dir(&dir).is_err());
+    }
+}
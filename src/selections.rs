@@ -0,0 +1,84 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+// A set of paths which remembers the order they were inserted in.
+#[derive(Debug, Default, Clone)]
+pub struct Selections {
+    paths: HashMap<PathBuf, usize>,
+    next: usize,
+}
+
+impl Selections {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn contains(&self, p: &Path) -> bool {
+        self.paths.contains_key(p)
+    }
+
+    // NOTE: inserting an existing path doesn't change its order.
+    pub fn insert(&mut self, p: PathBuf) {
+        if !self.paths.contains_key(&p) {
+            self.paths.insert(p, self.next);
+            self.next += 1;
+        }
+    }
+
+    pub fn remove(&mut self, p: &Path) -> bool {
+        self.paths.remove(p).is_some()
+    }
+
+    pub fn clear(&mut self) {
+        self.paths.clear();
+        self.next = 0;
+    }
+
+    pub fn len(&self) -> usize {
+        self.paths.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.paths.is_empty()
+    }
+
+    // Paths in the order they were inserted.
+    pub fn iter(&self) -> impl Iterator<Item = &PathBuf> {
+        let mut paths: Vec<_> = self.paths.iter().collect();
+        paths.sort_by_key(|(_, i)| **i);
+        paths.into_iter().map(|(p, _)| p)
+    }
+
+    pub fn to_vec(&self) -> Vec<PathBuf> {
+        self.iter().cloned().collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_order() {
+        let mut s = Selections::new();
+        s.insert(PathBuf::from("/c"));
+        s.insert(PathBuf::from("/a"));
+        s.insert(PathBuf::from("/b"));
+        s.insert(PathBuf::from("/c"));
+        assert!(s.remove(Path::new("/a")));
+        s.insert(PathBuf::from("/a"));
+        assert_eq!(
+            vec![
+                PathBuf::from("/c"),
+                PathBuf::from("/b"),
+                PathBuf::from("/a")
+            ],
+            s.to_vec()
+        );
+        assert_eq!(3, s.len());
+        s.clear();
+        assert!(s.is_empty());
+    }
+}
@@ -0,0 +1,123 @@
+use std::{fmt::Display, path::PathBuf};
+
+use crate::dir::SortType;
+use crate::utils;
+
+// NOTE: the state saved on quit and restored on startup, one `<key>\t<value>` per line.
+//
+// cwd	/home/user/Downloads
+// hidden	true
+// sort_type	natural
+// search	foo
+// cursor	/home/user	Downloads        # directory and the file under the cursor in it
+// select	/home/user/Downloads/a.zip
+// is_cut	false
+// cut_or_copy	/home/user/Downloads/b.zip
+pub fn session_file() -> PathBuf {
+    utils::data_dir().join("session")
+}
+
+#[derive(Debug, Default, PartialEq)]
+pub struct Session {
+    pub cwd: Option<PathBuf>,
+    pub hidden: Option<bool>,
+    pub sort_type: Option<SortType>,
+    pub search: String,
+    pub cursors: Vec<(PathBuf, String)>,
+    pub selections: Vec<PathBuf>,
+    pub is_cut: bool,
+    pub cut_or_copy: Vec<PathBuf>,
+}
+
+impl Session {
+    // NOTE: invalid lines are ignored
+    pub fn parse(content: &str) -> Self {
+        let mut session = Self::default();
+        for line in content.lines() {
+            let (key, val) = match line.split_once('\t') {
+                Some(kv) => kv,
+                None => {
+                    log::warn!("invalid session line `{line}`");
+                    continue;
+                }
+            };
+            match key {
+                "cwd" => session.cwd = Some(PathBuf::from(val)),
+                "hidden" => session.hidden = val.parse().ok(),
+                "sort_type" => session.sort_type = SortType::from_name(val),
+                "search" => session.search = val.to_string(),
+                "cursor" => {
+                    if let Some((dir, name)) = val.split_once('\t') {
+                        session.cursors.push((PathBuf::from(dir), name.to_string()));
+                    }
+                }
+                "select" => session.selections.push(PathBuf::from(val)),
+                "is_cut" => session.is_cut = val == "true",
+                "cut_or_copy" => session.cut_or_copy.push(PathBuf::from(val)),
+                _ => log::warn!("invalid session line `{line}`"),
+            }
+        }
+        session
+    }
+
+    pub fn load() -> Option<Self> {
+        let content = std::fs::read_to_string(session_file()).ok()?;
+        Some(Self::parse(&content))
+    }
+
+    pub fn save(&self) -> std::io::Result<()> {
+        utils::write_atomic(&session_file(), &self.to_string())
+    }
+}
+
+impl Display for Session {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(ref cwd) = self.cwd {
+            writeln!(f, "cwd\t{}", cwd.display())?;
+        }
+        if let Some(hidden) = self.hidden {
+            writeln!(f, "hidden\t{hidden}")?;
+        }
+        if let Some(sort_type) = self.sort_type {
+            writeln!(f, "sort_type\t{}", sort_type.name())?;
+        }
+        if !self.search.is_empty() {
+            writeln!(f, "search\t{}", self.search)?;
+        }
+        for (dir, name) in self.cursors.iter() {
+            writeln!(f, "cursor\t{}\t{name}", dir.display())?;
+        }
+        for p in self.selections.iter() {
+            writeln!(f, "select\t{}", p.display())?;
+        }
+        writeln!(f, "is_cut\t{}", self.is_cut)?;
+        for p in self.cut_or_copy.iter() {
+            writeln!(f, "cut_or_copy\t{}", p.display())?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let session = Session {
+            cwd: Some(PathBuf::from("/tmp")),
+            hidden: Some(false),
+            sort_type: Some(SortType::Size),
+            search: "foo bar".to_string(),
+            cursors: vec![(PathBuf::from("/"), "tmp".to_string())],
+            selections: vec![PathBuf::from("/tmp/b"), PathBuf::from("/tmp/a")],
+            is_cut: true,
+            cut_or_copy: vec![PathBuf::from("/tmp/c")],
+        };
+        assert_eq!(session, Session::parse(&session.to_string()));
+        assert_eq!(
+            Session::default(),
+            Session::parse("invalid\nno_such_key\t1\nis_cut\tfalse\n")
+        );
+    }
+}
@@ -0,0 +1,280 @@
+use simplelog::LevelFilter;
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+    sync::OnceLock,
+};
+
+use crate::config::{
+    CASE_INSENSITIVE, HIDDEN, HISTORY_SIZE, LOG_FILE_PATH, LOG_LEVEL, LS_COLORS, MAX_JOBS, OPENER,
+    PASTE_CONFLICT, PREVIEWER, SCROLL_OFF, SESSION, SORT_TYPE, WORD_SEPS,
+};
+use crate::conflict::Conflict;
+use crate::dir::SortType;
+use crate::utils;
+
+static SETTINGS: OnceLock<Settings> = OnceLock::new();
+
+// NOTE: runtime settings, the default values come from `src/config.rs`,
+// and they can be overridden by `$XDG_CONFIG_HOME/tfm/config`.
+//
+// # comment
+// scroll_off = 6
+// case_insensitive = true
+// word_seps = "/."
+// ls_colors = true                     # colour file entries using `LS_COLORS`
+// previewer = "~/code/tfm/script/preview"
+// opener = "~/code/tfm/script/open"
+// log_file = "~/.cache/rust-tfm/log"   # empty string means don't write log file
+// log_level = warn                     # off, error, warn, info, debug, trace
+// hidden = true                        # true: don't show hidden files
+// sort_type = natural                  # natural, mtime, size
+// session = false                      # save the session on quit and restore it on startup
+// history_size = 1000                  # max number of commands kept for each command prefix
+// paste_conflict = ask                 # ask, overwrite, skip, rename, overwrite-if-newer
+// max_jobs = 1                         # max number of pastes and removes running at the same time
+#[derive(Debug, Clone)]
+pub struct Settings {
+    pub scroll_off: u16,
+    pub case_insensitive: bool,
+    pub word_seps: Vec<char>,
+    pub ls_colors: bool,
+    pub previewer: String,
+    pub opener: String,
+    pub log_file: String,
+    pub log_level: LevelFilter,
+    pub hidden: bool,
+    pub sort_type: SortType,
+    pub session: bool,
+    pub history_size: usize,
+    pub paste_conflict: Conflict,
+    pub max_jobs: usize,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            scroll_off: SCROLL_OFF,
+            case_insensitive: CASE_INSENSITIVE,
+            word_seps: WORD_SEPS.to_vec(),
+            ls_colors: LS_COLORS,
+            previewer: PREVIEWER.to_string(),
+            opener: OPENER.to_string(),
+            log_file: LOG_FILE_PATH.to_string(),
+            log_level: LOG_LEVEL,
+            hidden: *HIDDEN.read().unwrap(),
+            sort_type: *SORT_TYPE.read().unwrap(),
+            session: SESSION,
+            history_size: HISTORY_SIZE,
+            paste_conflict: PASTE_CONFLICT,
+            max_jobs: MAX_JOBS,
+        }
+    }
+}
+
+impl Settings {
+    // NOTE: if `p` doesn't exist, the built-in defaults are used.
+    pub fn load(p: &Path) -> Result<Self, String> {
+        let mut settings = Self::default();
+        if !p.exists() {
+            return Ok(settings);
+        }
+        let content = std::fs::read_to_string(p)
+            .map_err(|err| format!("{err}: read config file `{p}` failed", p = p.display()))?;
+        for (i, line) in content.lines().enumerate() {
+            settings
+                .parse_line(line)
+                .map_err(|err| format!("{p}:{n}: {err}", p = p.display(), n = i + 1))?;
+        }
+        Ok(settings)
+    }
+
+    fn parse_line(&mut self, line: &str) -> Result<(), String> {
+        let line = strip_comment(line).trim();
+        if line.is_empty() {
+            return Ok(());
+        }
+        let (key, val) = match line.split_once('=') {
+            Some((key, val)) => (key.trim(), unquote(val.trim())?),
+            None => return Err(format!("expected `key = value`, found `{line}`")),
+        };
+        match key {
+            "scroll_off" => {
+                self.scroll_off = val
+                    .parse()
+                    .map_err(|_| format!("`{key}` expects a number, found `{val}`"))?
+            }
+            "case_insensitive" => self.case_insensitive = parse_bool(key, val)?,
+            "word_seps" => self.word_seps = val.chars().collect(),
+            "ls_colors" => self.ls_colors = parse_bool(key, val)?,
+            "previewer" => self.previewer = val.to_string(),
+            "opener" => self.opener = val.to_string(),
+            "log_file" => self.log_file = val.to_string(),
+            "log_level" => self.log_level = parse_log_level(val)?,
+            "hidden" => self.hidden = parse_bool(key, val)?,
+            "sort_type" => {
+                self.sort_type = SortType::from_name(val).ok_or(format!(
+                    "`{key}` expects one of natural|mtime|size, found `{val}`"
+                ))?
+            }
+            "session" => self.session = parse_bool(key, val)?,
+            "history_size" => {
+                self.history_size = val
+                    .parse()
+                    .map_err(|_| format!("`{key}` expects a number, found `{val}`"))?
+            }
+            "paste_conflict" => {
+                self.paste_conflict = Conflict::from_name(val).ok_or(format!(
+                    "`{key}` expects one of ask|overwrite|skip|rename|overwrite-if-newer, found `{val}`"
+                ))?
+            }
+            "max_jobs" => {
+                self.max_jobs = val
+                    .parse()
+                    .ok()
+                    .filter(|n| *n > 0)
+                    .ok_or(format!("`{key}` expects a positive number, found `{val}`"))?
+            }
+            _ => return Err(format!("unknown option `{key}`")),
+        }
+        Ok(())
+    }
+}
+
+// NOTE: the output can be loaded by `Settings::load`, it's used by `--print-default-config`.
+impl fmt::Display for Settings {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "scroll_off = {}", self.scroll_off)?;
+        writeln!(f, "case_insensitive = {}", self.case_insensitive)?;
+        writeln!(
+            f,
+            "word_seps = \"{}\"",
+            self.word_seps.iter().collect::<String>()
+        )?;
+        writeln!(f, "ls_colors = {}", self.ls_colors)?;
+        writeln!(f, "previewer = \"{}\"", self.previewer)?;
+        writeln!(f, "opener = \"{}\"", self.opener)?;
+        writeln!(f, "log_file = \"{}\"", self.log_file)?;
+        writeln!(f, "log_level = {}", self.log_level.as_str().to_lowercase())?;
+        writeln!(f, "hidden = {}", self.hidden)?;
+        writeln!(f, "sort_type = {}", self.sort_type.name())?;
+        writeln!(f, "session = {}", self.session)?;
+        writeln!(f, "history_size = {}", self.history_size)?;
+        writeln!(f, "paste_conflict = {}", self.paste_conflict.name())?;
+        writeln!(f, "max_jobs = {}", self.max_jobs)
+    }
+}
+
+pub fn parse_log_level(val: &str) -> Result<LevelFilter, String> {
+    match val {
+        "off" => Ok(LevelFilter::Off),
+        "error" => Ok(LevelFilter::Error),
+        "warn" => Ok(LevelFilter::Warn),
+        "info" => Ok(LevelFilter::Info),
+        "debug" => Ok(LevelFilter::Debug),
+        "trace" => Ok(LevelFilter::Trace),
+        _ => Err(format!(
+            "`log_level` expects one of off|error|warn|info|debug|trace, found `{val}`"
+        )),
+    }
+}
+
+fn strip_comment(line: &str) -> &str {
+    let mut quoted = false;
+    for (i, ch) in line.char_indices() {
+        match ch {
+            '"' => quoted ^= true,
+            '#' if !quoted => return &line[..i],
+            _ => {}
+        }
+    }
+    line
+}
+
+fn unquote(val: &str) -> Result<&str, String> {
+    if let Some(rest) = val.strip_prefix('"') {
+        match rest.strip_suffix('"') {
+            Some(s) => Ok(s),
+            None => Err(format!("unterminated string `{val}`")),
+        }
+    } else {
+        Ok(val)
+    }
+}
+
+fn parse_bool(key: &str, val: &str) -> Result<bool, String> {
+    match val {
+        "true" => Ok(true),
+        "false" => Ok(false),
+        _ => Err(format!("`{key}` expects true or false, found `{val}`")),
+    }
+}
+
+pub fn config_file() -> PathBuf {
+    utils::config_dir().join("config")
+}
+
+// NOTE: must be called at most once, and before the first `settings()` call.
+pub fn init(settings: Settings) {
+    *HIDDEN.write().unwrap() = settings.hidden;
+    *SORT_TYPE.write().unwrap() = settings.sort_type;
+    if SETTINGS.set(settings).is_err() {
+        log::error!("settings already initialized!!!");
+    }
+}
+
+pub fn settings() -> &'static Settings {
+    SETTINGS.get_or_init(Settings::default)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_line() {
+        let mut s = Settings::default();
+        s.parse_line("scroll_off = 3 # comment").unwrap();
+        s.parse_line("  # only comment").unwrap();
+        s.parse_line("").unwrap();
+        s.parse_line("previewer = \"~/bin/preview #1\"").unwrap();
+        s.parse_line("sort_type = mtime").unwrap();
+        s.parse_line("word_seps = \"/._\"").unwrap();
+        s.parse_line("log_file = \"\"").unwrap();
+        s.parse_line("paste_conflict = overwrite-if-newer").unwrap();
+        s.parse_line("max_jobs = 2").unwrap();
+        assert_eq!(3, s.scroll_off);
+        assert_eq!("~/bin/preview #1", s.previewer);
+        assert_eq!(SortType::ModifyTime, s.sort_type);
+        assert_eq!(vec!['/', '.', '_'], s.word_seps);
+        assert!(s.log_file.is_empty());
+        assert_eq!(Conflict::OverwriteIfNewer, s.paste_conflict);
+        assert_eq!(2, s.max_jobs);
+    }
+
+    #[test]
+    fn test_display() {
+        let s = Settings {
+            sort_type: SortType::Size,
+            word_seps: vec!['-'],
+            ..Default::default()
+        };
+        let mut t = Settings::default();
+        for line in s.to_string().lines() {
+            t.parse_line(line).unwrap();
+        }
+        assert_eq!(s.to_string(), t.to_string());
+    }
+
+    #[test]
+    fn test_parse_line_error() {
+        let mut s = Settings::default();
+        assert!(s.parse_line("scroll_off").is_err());
+        assert!(s.parse_line("scroll_off = -1").is_err());
+        assert!(s.parse_line("hidden = yes").is_err());
+        assert!(s.parse_line("opener = \"/bin/open").is_err());
+        assert!(s.parse_line("no_such_option = 1").is_err());
+        assert!(s.parse_line("paste_conflict = newer").is_err());
+        assert!(s.parse_line("max_jobs = 0").is_err());
+    }
+}
@@ -0,0 +1,420 @@
+use crossterm::style::Color;
+use std::{collections::HashMap, path::Path, sync::OnceLock};
+
+use crate::buffer::{Attr, Attrs, Style};
+use crate::config::theme as default;
+use crate::file::{File, FileType, LinkState};
+use crate::settings::settings;
+use crate::utils;
+
+static THEME: OnceLock<Theme> = OnceLock::new();
+
+// NOTE: `$XDG_CONFIG_HOME/tfm/theme` overrides the default styles in `src/config.rs`.
+//
+// # element = fg bg [attrs]
+// dir = blue reset bold
+// link_broken = #ff0000 reset
+// selection = 0 13                     # 256 colors
+// error_msg = black red bold,underline
+//
+// colors: reset, black, red, green, yellow, blue, magenta, cyan, white, grey,
+//         dark_grey, dark_red, dark_green, dark_yellow, dark_blue, dark_magenta, dark_cyan,
+//         0-255 or #rrggbb
+// attrs: bold, dim, italic, underline, reverse, hidden, crossed_out, separated by `,`
+macro_rules! theme {
+    ($($field:ident => $default:ident),* $(,)?) => {
+        pub struct Theme {
+            $(pub $field: Style,)*
+            pub ls_colors: Option<LsColors>,
+        }
+
+        impl Default for Theme {
+            fn default() -> Self {
+                Self {
+                    $($field: default::$default,)*
+                    ls_colors: None,
+                }
+            }
+        }
+
+        impl Theme {
+            fn style_mut(&mut self, name: &str) -> Option<&mut Style> {
+                match name {
+                    $(stringify!($field) => Some(&mut self.$field),)*
+                    _ => None,
+                }
+            }
+        }
+    };
+}
+
+theme! {
+    user => USER_STYLE,
+    executable_file => EXECUTABLE_FILE_STYLE,
+    reg_file => REG_FILE_STYLE,
+    dir => DIR_STYLE,
+    link_working => LINK_WORKING_STYLE,
+    link_broken => LINK_BROKEN_STYLE,
+    pipe_file => PIPE_FILE_STYLE,
+    socket_file => SOCKET_FILE_STYLE,
+    char_file => CHAR_FILE_STYLE,
+    block_file => BLOCK_FILE_STYLE,
+    ui_border => UI_BORDER_STYLE,
+    warn_msg => WARN_MSG_STYLE,
+    error_msg => ERROR_MSG_STYLE,
+    file_info => FILE_INFO_STYLE,
+    proportion => PROPORTION_STYLE,
+    progress => PROGRESS_STYLE,
+    selection => SELECTION_STYLE,
+    cut => CUT_STYLE,
+    copy => COPY_STYLE,
+}
+
+impl Theme {
+    // NOTE: if `p` doesn't exist, the default styles are used.
+    pub fn load(p: &Path) -> Result<Self, String> {
+        let mut theme = Self::default();
+        if settings().ls_colors {
+            if let Ok(s) = std::env::var("LS_COLORS") {
+                theme.ls_colors = Some(LsColors::parse(&s));
+            }
+        }
+        if !p.exists() {
+            return Ok(theme);
+        }
+        let content = std::fs::read_to_string(p)
+            .map_err(|err| format!("{err}: read theme file `{p}` failed", p = p.display()))?;
+        for (i, line) in content.lines().enumerate() {
+            theme
+                .parse_line(line)
+                .map_err(|err| format!("{p}:{n}: {err}", p = p.display(), n = i + 1))?;
+        }
+        Ok(theme)
+    }
+
+    fn parse_line(&mut self, line: &str) -> Result<(), String> {
+        let line = strip_comment(line.trim()).trim();
+        if line.is_empty() {
+            return Ok(());
+        }
+        let (name, val) = match line.split_once('=') {
+            Some((name, val)) => (name.trim(), val.trim()),
+            None => {
+                return Err(format!(
+                    "expected `element = fg bg [attrs]`, found `{line}`"
+                ))
+            }
+        };
+        let style = match self.style_mut(name) {
+            Some(style) => style,
+            None => return Err(format!("unknown element `{name}`")),
+        };
+        let vals: Vec<_> = val.split_whitespace().collect();
+        if vals.len() < 2 || vals.len() > 3 {
+            return Err(format!("expected `fg bg [attrs]`, found `{val}`"));
+        }
+        let fg = parse_color(vals[0])?;
+        let bg = parse_color(vals[1])?;
+        let attrs = match vals.get(2) {
+            Some(attrs) => parse_attrs(attrs)?,
+            None => Attrs::default(),
+        };
+        *style = Style::new(fg, bg, attrs);
+        Ok(())
+    }
+
+    // Style of a file entry, `LS_COLORS` takes precedence over the file type styles.
+    pub fn file_style(&self, file: &File) -> Style {
+        if let Some(ref ls_colors) = self.ls_colors {
+            if let Some(style) = ls_colors.file_style(file) {
+                return style;
+            }
+        }
+        match file.file_type {
+            FileType::RegularFile => {
+                if file.is_executable() {
+                    self.executable_file
+                } else {
+                    self.reg_file
+                }
+            }
+            FileType::Directory => self.dir,
+            FileType::Link => match file.link_state {
+                Some(ref state) => match state {
+                    LinkState::Working(_) => self.link_working,
+                    LinkState::Broken(_) => self.link_broken,
+                },
+                None => self.reg_file,
+            },
+            FileType::Pipe => self.pipe_file,
+            FileType::Socket => self.socket_file,
+            FileType::CharDevice => self.char_file,
+            FileType::BlockDevice => self.block_file,
+            _ => self.reg_file,
+        }
+    }
+}
+
+// NOTE: `#` starts a comment only at the beginning of a line or when followed by a whitespace,
+// otherwise it's a `#rrggbb` color.
+fn strip_comment(line: &str) -> &str {
+    for (i, _) in line.match_indices('#') {
+        if i == 0 || line[i + 1..].chars().next().is_none_or(char::is_whitespace) {
+            return &line[..i];
+        }
+    }
+    line
+}
+
+fn parse_color(s: &str) -> Result<Color, String> {
+    let color = match s {
+        "reset" | "default" => Color::Reset,
+        "black" => Color::Black,
+        "red" => Color::Red,
+        "green" => Color::Green,
+        "yellow" => Color::Yellow,
+        "blue" => Color::Blue,
+        "magenta" => Color::Magenta,
+        "cyan" => Color::Cyan,
+        "white" => Color::White,
+        "grey" => Color::Grey,
+        "dark_grey" => Color::DarkGrey,
+        "dark_red" => Color::DarkRed,
+        "dark_green" => Color::DarkGreen,
+        "dark_yellow" => Color::DarkYellow,
+        "dark_blue" => Color::DarkBlue,
+        "dark_magenta" => Color::DarkMagenta,
+        "dark_cyan" => Color::DarkCyan,
+        _ => {
+            if let Some(hex) = s.strip_prefix('#') {
+                match u32::from_str_radix(hex, 16) {
+                    Ok(rgb) if hex.len() == 6 => Color::Rgb {
+                        r: (rgb >> 16) as u8,
+                        g: (rgb >> 8) as u8,
+                        b: rgb as u8,
+                    },
+                    _ => return Err(format!("invalid rgb color `{s}`, expected `#rrggbb`")),
+                }
+            } else {
+                match s.parse::<u8>() {
+                    Ok(n) => Color::AnsiValue(n),
+                    Err(_) => return Err(format!("unknown color `{s}`")),
+                }
+            }
+        }
+    };
+    Ok(color)
+}
+
+fn parse_attrs(s: &str) -> Result<Attrs, String> {
+    let mut attrs = Attrs::default();
+    for attr in s.split(',') {
+        match attr {
+            "none" => {}
+            "bold" => attrs.set(Attr::Bold),
+            "dim" => attrs.set(Attr::Dim),
+            "italic" => attrs.set(Attr::Italic),
+            "underline" => attrs.set(Attr::Underline),
+            "reverse" => attrs.set(Attr::Reverse),
+            "hidden" => attrs.set(Attr::Hide),
+            "crossed_out" => attrs.set(Attr::CrossedOut),
+            _ => return Err(format!("unknown attribute `{attr}`")),
+        }
+    }
+    Ok(attrs)
+}
+
+// See `man dir_colors`, only the keys below are used:
+// di ln or mi pi so bd cd ex su sg fi and `*<suffix>`
+pub struct LsColors {
+    types: HashMap<String, Style>,
+    suffixes: Vec<(String, Style)>,
+    link_target: bool, // `ln=target`
+}
+
+impl LsColors {
+    pub fn parse(s: &str) -> Self {
+        let mut ret = Self {
+            types: HashMap::new(),
+            suffixes: Vec::new(),
+            link_target: false,
+        };
+        // NOTE: invalid entries are ignored like `ls` does.
+        for entry in s.split(':') {
+            let (key, codes) = match entry.split_once('=') {
+                Some(kv) => kv,
+                None => continue,
+            };
+            if key == "ln" && codes == "target" {
+                ret.link_target = true;
+                continue;
+            }
+            let mut style = Style::default();
+            style.parse_ansi_code(codes);
+            if let Some(suffix) = key.strip_prefix('*') {
+                ret.suffixes.push((suffix.to_string(), style));
+            } else {
+                ret.types.insert(key.to_string(), style);
+            }
+        }
+        ret
+    }
+
+    fn get(&self, key: &str) -> Option<Style> {
+        self.types.get(key).copied()
+    }
+
+    fn suffix_style(&self, name: &str) -> Option<Style> {
+        // NOTE: the later entry wins, and the exact case wins over ignoring case.
+        let lower = name.to_ascii_lowercase();
+        let mut fallback = None;
+        for (suffix, style) in self.suffixes.iter().rev() {
+            if name.ends_with(suffix.as_str()) {
+                return Some(*style);
+            }
+            if fallback.is_none() && lower.ends_with(&suffix.to_ascii_lowercase()) {
+                fallback = Some(*style);
+            }
+        }
+        fallback
+    }
+
+    fn reg_file_style(
+        &self,
+        name: &str,
+        executable: bool,
+        setuid: bool,
+        setgid: bool,
+    ) -> Option<Style> {
+        if setuid {
+            if let Some(style) = self.get("su") {
+                return Some(style);
+            }
+        }
+        if setgid {
+            if let Some(style) = self.get("sg") {
+                return Some(style);
+            }
+        }
+        if executable {
+            if let Some(style) = self.get("ex") {
+                return Some(style);
+            }
+        }
+        self.suffix_style(name).or_else(|| self.get("fi"))
+    }
+
+    pub fn file_style(&self, file: &File) -> Option<Style> {
+        match file.file_type {
+            FileType::RegularFile => self.reg_file_style(
+                &file.file_name,
+                file.is_executable(),
+                file.is_setuid(),
+                file.is_setgid(),
+            ),
+            FileType::Directory => self.get("di"),
+            FileType::Link => match file.link_state {
+                Some(LinkState::Broken(_)) => self.get("or").or_else(|| self.get("ln")),
+                _ if self.link_target => match file.file_path.metadata() {
+                    Ok(metadata) if metadata.is_dir() => self.get("di"),
+                    Ok(metadata) => {
+                        use std::os::unix::fs::PermissionsExt;
+                        let mode = metadata.permissions().mode();
+                        self.reg_file_style(&file.file_name, mode & 0o111 != 0, false, false)
+                    }
+                    Err(_) => self.get("or"),
+                },
+                _ => self.get("ln"),
+            },
+            FileType::Pipe => self.get("pi"),
+            FileType::Socket => self.get("so"),
+            FileType::CharDevice => self.get("cd"),
+            FileType::BlockDevice => self.get("bd"),
+            _ => None,
+        }
+    }
+
+    // Style of the broken link destination shown in the status line
+    pub fn missing_style(&self) -> Option<Style> {
+        self.get("mi")
+    }
+}
+
+pub fn theme_file() -> std::path::PathBuf {
+    utils::config_dir().join("theme")
+}
+
+// NOTE: must be called at most once, and before the first `theme()` call.
+pub fn init(theme: Theme) {
+    if THEME.set(theme).is_err() {
+        log::error!("theme already initialized!!!");
+    }
+}
+
+pub fn theme() -> &'static Theme {
+    THEME.get_or_init(Theme::default)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_line() {
+        let mut theme = Theme::default();
+        theme
+            .parse_line("dir = #ff0010 reset bold,italic # comment")
+            .unwrap();
+        theme.parse_line("selection = 0 13").unwrap();
+        theme.parse_line("# comment").unwrap();
+        let mut attrs = Attrs::default();
+        attrs.set(Attr::Bold);
+        attrs.set(Attr::Italic);
+        assert_eq!(
+            Style::new(
+                Color::Rgb {
+                    r: 255,
+                    g: 0,
+                    b: 16
+                },
+                Color::Reset,
+                attrs
+            ),
+            theme.dir
+        );
+        assert_eq!(
+            Style::new(Color::AnsiValue(0), Color::AnsiValue(13), Attrs::default()),
+            theme.selection
+        );
+        assert!(theme.parse_line("no_such_element = red reset").is_err());
+        assert!(theme.parse_line("dir = red").is_err());
+        assert!(theme.parse_line("dir = purple reset").is_err());
+        assert!(theme.parse_line("dir = red reset blink").is_err());
+    }
+
+    #[test]
+    fn test_ls_colors() {
+        let ls = LsColors::parse("di=01;34:ex=01;32:su=37;41:*.rs=33:*.TAR=31:bad_entry");
+        assert_eq!(Some(Color::DarkBlue), ls.get("di").map(|s| s.fg));
+        assert_eq!(
+            Some(Color::DarkYellow),
+            ls.reg_file_style("main.rs", false, false, false)
+                .map(|s| s.fg)
+        );
+        assert_eq!(
+            Some(Color::DarkRed),
+            ls.reg_file_style("a.tar", false, false, false)
+                .map(|s| s.fg)
+        );
+        assert_eq!(
+            Some(Color::DarkGreen),
+            ls.reg_file_style("run.rs", true, false, false)
+                .map(|s| s.fg)
+        );
+        assert_eq!(
+            Some(Color::DarkRed),
+            ls.reg_file_style("passwd", true, true, false).map(|s| s.bg)
+        );
+        assert_eq!(None, ls.reg_file_style("README", false, false, false));
+    }
+}
@@ -0,0 +1,376 @@
+use std::{
+    fs,
+    io::{ErrorKind, Write},
+    os::unix::fs::{DirBuilderExt, MetadataExt},
+    path::{Path, PathBuf},
+};
+
+use chrono::Local;
+
+use crate::utils;
+
+// NOTE: the freedesktop.org trash(https://specifications.freedesktop.org/trash-spec/latest/),
+// a trashed file is moved to `<trash>/files/<name>`, and `<trash>/info/<name>.trashinfo` records
+// where it was and when it was trashed.
+//
+// [Trash Info]
+// Path=/home/user/a%20b.txt
+// DeletionDate=2024-01-02T03:04:05
+//
+// Files on the same filesystem as the home trash(`$XDG_DATA_HOME/Trash`) go to it, others go to
+// `<mount point>/.Trash/$uid`(if `.Trash` has the sticky bit) or `<mount point>/.Trash-$uid`, and
+// `Path` is relative to the mount point there.
+pub struct Trash {
+    pub dir: PathBuf,
+    pub top: Option<PathBuf>, // NOTE: the mount point of a per-mount trash, None for the home trash
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct TrashEntry {
+    pub info: PathBuf,
+    pub file: PathBuf,
+    pub path: PathBuf, // where the file was
+    pub deletion_date: String,
+}
+
+fn home_trash() -> PathBuf {
+    utils::data_dir().with_file_name("Trash")
+}
+
+impl Trash {
+    fn new(dir: PathBuf, top: Option<PathBuf>) -> Self {
+        Self { dir, top }
+    }
+
+    fn create_dirs(&self) -> std::io::Result<()> {
+        let mut builder = fs::DirBuilder::new();
+        builder.recursive(true).mode(0o700);
+        builder.create(self.dir.join("files"))?;
+        builder.create(self.dir.join("info"))
+    }
+
+    // Move `p`(an absolute path) into the trash.
+    pub fn put(&self, p: &Path) -> Result<TrashEntry, String> {
+        let base = match p.file_name() {
+            Some(name) => name.to_string_lossy().to_string(),
+            None => return Err(format!("can't trash `{}`", p.display())),
+        };
+        self.create_dirs()
+            .map_err(|err| format!("{err}: create trash `{}` failed", self.dir.display()))?;
+        let path = match self.top {
+            Some(ref top) => p.strip_prefix(top).unwrap_or(p),
+            None => p,
+        };
+        let deletion_date = Local::now().format("%Y-%m-%dT%H:%M:%S").to_string();
+        let content = format!(
+            "[Trash Info]\nPath={path}\nDeletionDate={deletion_date}\n",
+            path = encode(&path.to_string_lossy()),
+        );
+        // NOTE: creating the `.trashinfo` file with `create_new` reserves the name atomically
+        for i in 1.. {
+            let name = if i == 1 {
+                base.clone()
+            } else {
+                format!("{base}.{i}")
+            };
+            let info = self.dir.join("info").join(format!("{name}.trashinfo"));
+            let file = self.dir.join("files").join(&name);
+            let mut f = match fs::File::options().write(true).create_new(true).open(&info) {
+                Ok(f) => f,
+                Err(err) if err.kind() == ErrorKind::AlreadyExists => continue,
+                Err(err) => return Err(format!("{err}: create `{}` failed", info.display())),
+            };
+            if file.symlink_metadata().is_ok() {
+                let _ = fs::remove_file(&info);
+                continue;
+            }
+            let res = f
+                .write_all(content.as_bytes())
+                .and_then(|_| fs::rename(p, &file));
+            if let Err(err) = res {
+                let _ = fs::remove_file(&info);
+                return Err(format!("{err}: trash `{}` failed", p.display()));
+            }
+            return Ok(TrashEntry {
+                info,
+                file,
+                path: p.to_path_buf(),
+                deletion_date,
+            });
+        }
+        unreachable!()
+    }
+
+    // NOTE: invalid `.trashinfo` files and the ones without the trashed file are ignored
+    pub fn entries(&self) -> Vec<TrashEntry> {
+        let mut ret = Vec::new();
+        let iter = match fs::read_dir(self.dir.join("info")) {
+            Ok(iter) => iter,
+            Err(_) => return ret,
+        };
+        for entry in iter.flatten() {
+            let info = entry.path();
+            let name = match info.file_name().and_then(|n| n.to_str()) {
+                Some(name) => match name.strip_suffix(".trashinfo") {
+                    Some(name) => name.to_string(),
+                    None => continue,
+                },
+                None => continue,
+            };
+            let file = self.dir.join("files").join(name);
+            if file.symlink_metadata().is_err() {
+                continue;
+            }
+            let (path, deletion_date) = match fs::read_to_string(&info)
+                .ok()
+                .and_then(|content| parse_info(&content))
+            {
+                Some(v) => v,
+                None => {
+                    log::warn!("invalid trash info `{}`", info.display());
+                    continue;
+                }
+            };
+            let path = match self.top {
+                Some(ref top) => top.join(path),
+                None => PathBuf::from(path),
+            };
+            ret.push(TrashEntry {
+                info,
+                file,
+                path,
+                deletion_date,
+            });
+        }
+        ret
+    }
+}
+
+fn uid() -> u32 {
+    users::get_current_uid()
+}
+
+// NOTE: the top most directory on the same filesystem as `p`
+fn mount_point(p: &Path) -> PathBuf {
+    let dev = match p.symlink_metadata() {
+        Ok(metadata) => metadata.dev(),
+        Err(_) => return PathBuf::from("/"),
+    };
+    let mut top = p;
+    for dir in p.ancestors().skip(1) {
+        match dir.metadata() {
+            Ok(metadata) if metadata.dev() == dev => top = dir,
+            _ => break,
+        }
+    }
+    top.to_path_buf()
+}
+
+// The per-mount trash directories of the mount point `top`.
+fn mount_trashes(top: &Path) -> [PathBuf; 2] {
+    [
+        top.join(".Trash").join(uid().to_string()),
+        top.join(format!(".Trash-{}", uid())),
+    ]
+}
+
+// NOTE: `.Trash` must be a real directory with the sticky bit, otherwise it's ignored.
+fn admin_trash_ok(top: &Path) -> bool {
+    match top.join(".Trash").symlink_metadata() {
+        Ok(metadata) => metadata.is_dir() && metadata.mode() & 0o1000 != 0,
+        Err(_) => false,
+    }
+}
+
+// The trash `p` should be moved to.
+fn trash_for(p: &Path) -> Result<Trash, String> {
+    let home = home_trash();
+    fs::create_dir_all(&home)
+        .map_err(|err| format!("{err}: create trash `{}` failed", home.display()))?;
+    let dev = p
+        .symlink_metadata()
+        .map_err(|err| format!("{err}: trash `{}` failed", p.display()))?
+        .dev();
+    if home.metadata().is_ok_and(|m| m.dev() == dev) {
+        return Ok(Trash::new(home, None));
+    }
+    let top = mount_point(p);
+    let [admin, user] = mount_trashes(&top);
+    let dir = if admin_trash_ok(&top) { admin } else { user };
+    Ok(Trash::new(dir, Some(top)))
+}
+
+// Trash `p`, a relative path is relative to the current directory.
+pub fn put(p: &Path) -> Result<TrashEntry, String> {
+    let p = match (p.parent(), p.file_name()) {
+        (Some(parent), Some(name)) => {
+            let parent = if parent.as_os_str().is_empty() {
+                Path::new(".")
+            } else {
+                parent
+            };
+            parent
+                .canonicalize()
+                .map_err(|err| format!("{err}: trash `{}` failed", p.display()))?
+                .join(name)
+        }
+        _ => return Err(format!("can't trash `{}`", p.display())),
+    };
+    trash_for(&p)?.put(&p)
+}
+
+// NOTE: mount points are read from `/proc/self/mounts`, and only the home trash is used if it
+// doesn't exist.
+fn all_trashes() -> Vec<Trash> {
+    let mut ret = vec![Trash::new(home_trash(), None)];
+    let mounts = fs::read_to_string("/proc/self/mounts").unwrap_or_default();
+    for line in mounts.lines() {
+        let top = match line.split(' ').nth(1) {
+            Some(top) => PathBuf::from(unescape_mount(top)),
+            None => continue,
+        };
+        for dir in mount_trashes(&top) {
+            if dir.is_dir() && ret.iter().all(|t| t.dir != dir) {
+                ret.push(Trash::new(dir, Some(top.clone())));
+            }
+        }
+    }
+    ret
+}
+
+// All trashed files, the newest first.
+pub fn list() -> Vec<TrashEntry> {
+    let mut ret: Vec<_> = all_trashes().iter().flat_map(|t| t.entries()).collect();
+    ret.sort_by(|a, b| b.deletion_date.cmp(&a.deletion_date));
+    ret
+}
+
+// NOTE: a file which already exists at the original path isn't overwritten.
+pub fn restore(entry: &TrashEntry) -> Result<(), String> {
+    if entry.path.symlink_metadata().is_ok() {
+        return Err(format!("`{}` already exists", entry.path.display()));
+    }
+    if let Some(parent) = entry.path.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|err| format!("{err}: create `{}` failed", parent.display()))?;
+    }
+    fs::rename(&entry.file, &entry.path)
+        .map_err(|err| format!("{err}: restore `{}` failed", entry.path.display()))?;
+    fs::remove_file(&entry.info)
+        .map_err(|err| format!("{err}: remove `{}` failed", entry.info.display()))
+}
+
+// Delete a trashed file for good.
+pub fn delete(entry: &TrashEntry) -> Result<(), String> {
+    utils::dir_remove(&entry.file)
+        .map_err(|err| format!("{err}: delete `{}` failed", entry.file.display()))?;
+    fs::remove_file(&entry.info)
+        .map_err(|err| format!("{err}: remove `{}` failed", entry.info.display()))
+}
+
+// (Path, DeletionDate) of a `.trashinfo` file, `Path` is decoded.
+fn parse_info(content: &str) -> Option<(String, String)> {
+    let mut lines = content.lines();
+    if lines.next()?.trim() != "[Trash Info]" {
+        return None;
+    }
+    let (mut path, mut date) = (None, String::new());
+    for line in lines {
+        match line.split_once('=') {
+            Some(("Path", val)) => path = decode(val),
+            Some(("DeletionDate", val)) => date = val.to_string(),
+            _ => {}
+        }
+    }
+    Some((path?, date))
+}
+
+// NOTE: percent-encode everything except unreserved characters and `/`, like URIs.
+fn encode(s: &str) -> String {
+    let mut ret = String::new();
+    for b in s.bytes() {
+        if b.is_ascii_alphanumeric() || b"-_.~/".contains(&b) {
+            ret.push(b as char);
+        } else {
+            ret += &format!("%{b:02X}");
+        }
+    }
+    ret
+}
+
+fn decode(s: &str) -> Option<String> {
+    let mut bytes = Vec::new();
+    let mut iter = s.bytes();
+    while let Some(b) = iter.next() {
+        if b == b'%' {
+            let hex = [iter.next()?, iter.next()?];
+            bytes.push(u8::from_str_radix(std::str::from_utf8(&hex).ok()?, 16).ok()?);
+        } else {
+            bytes.push(b);
+        }
+    }
+    Some(String::from_utf8_lossy(&bytes).to_string())
+}
+
+// NOTE: blanks in `/proc/self/mounts` are escaped like `\040`
+fn unescape_mount(s: &str) -> String {
+    let mut ret = String::new();
+    let mut rest = s;
+    while let Some(i) = rest.find('\\') {
+        ret += &rest[..i];
+        match u8::from_str_radix(rest.get(i + 1..i + 4).unwrap_or_default(), 8) {
+            Ok(b) => {
+                ret.push(b as char);
+                rest = &rest[i + 4..];
+            }
+            Err(_) => {
+                ret.push('\\');
+                rest = &rest[i + 1..];
+            }
+        }
+    }
+    ret + rest
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_info() {
+        let path = "/tmp/a b/%中文.txt";
+        assert_eq!("/tmp/a%20b/%25%E4%B8%AD%E6%96%87.txt", encode(path));
+        assert_eq!(Some(path.to_string()), decode(&encode(path)));
+        assert_eq!(None, decode("%2"));
+        assert_eq!(
+            Some(("/tmp/a b".to_string(), "2024-01-02T03:04:05".to_string())),
+            parse_info("[Trash Info]\nPath=/tmp/a%20b\nDeletionDate=2024-01-02T03:04:05\n")
+        );
+        assert_eq!(None, parse_info("Path=/tmp\n"));
+        assert_eq!("/mnt/a b\\x", unescape_mount("/mnt/a\\040b\\x"));
+    }
+
+    #[test]
+    fn test_put_restore() {
+        let dir = utils::TempDir::new("trash");
+        let trash = Trash::new(dir.join("trash"), Some(dir.to_path_buf()));
+        let file = dir.join("a b");
+        for i in 0..2 {
+            fs::create_dir_all(&dir).unwrap();
+            fs::write(&file, format!("{i}")).unwrap();
+            trash.put(&file).unwrap();
+            assert!(!file.exists());
+        }
+        let mut entries = trash.entries();
+        entries.sort_by(|a, b| a.file.cmp(&b.file));
+        assert_eq!(2, entries.len());
+        assert_eq!(trash.dir.join("files/a b.2"), entries[1].file);
+        assert!(entries.iter().all(|e| e.path == file));
+
+        restore(&entries[1]).unwrap();
+        assert_eq!("1", fs::read_to_string(&file).unwrap());
+        assert!(restore(&entries[0]).is_err());
+        delete(&entries[0]).unwrap();
+        assert!(trash.entries().is_empty());
+    }
+}
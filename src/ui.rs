@@ -4,11 +4,13 @@ use crossterm::{cursor, execute, queue, terminal};
 use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
 
 use crate::buffer::{Attr, Buffer, Style};
-use crate::config::theme;
+use crate::complete::Completion;
 use crate::dir::Dir;
 use crate::file::{File, FileType, LinkState};
+use crate::menu::{Menu, MenuKind};
 use crate::nav::Nav;
 use crate::reg::Reg;
+use crate::theme::theme;
 use crate::utils;
 
 /*
@@ -31,7 +33,7 @@ use crate::utils;
 */
 
 pub struct Ui {
-    out: Box<dyn std::io::Write>, // TODO: maybe just use `Stdout` instead of `Box<...>`
+    out: Box<dyn std::io::Write>, // `Stdout`, or `/dev/tty` if stdout is redirected
     buffer: Buffer,
     wins: [u16; 4],
     user: String,
@@ -45,7 +47,7 @@ impl Ui {
         let buffer = Buffer::new();
         let (cols, rows) = buffer.size;
         Self {
-            out: Box::new(std::io::stdout()),
+            out: utils::tty_out(),
             buffer,
             user: std::env::var("USER").unwrap_or_default(),
             wins: [0, cols / 6, cols / 2, cols - 1],
@@ -62,7 +64,7 @@ impl Ui {
 
     fn draw_border(&mut self) {
         let (cols, rows) = self.buffer.size;
-        let style = theme::UI_BORDER_STYLE;
+        let style = theme().ui_border;
         for i in 1..cols - 1 {
             self.buffer.set_content(i, 1, '─', &style);
             self.buffer.set_content(i, rows - 2, '─', &style);
@@ -84,7 +86,7 @@ impl Ui {
         self.buffer.set_content(cols - 1, rows - 2, '┘', &style);
     }
 
-    pub fn draw(&mut self, nav: &Nav) -> std::io::Result<()> {
+    pub fn draw(&mut self, nav: &Nav, prompt: Option<&str>) -> std::io::Result<()> {
         for i in 0..self.buffer.size.0 {
             for j in 0..self.buffer.size.1 {
                 self.buffer.set_content(i, j, ' ', &Style::default());
@@ -125,16 +127,125 @@ impl Ui {
             self.draw_warn_message(2, "loading...");
         }
 
+        if let Some(ref menu) = nav.menu {
+            self.draw_menu(menu);
+        }
+        if let Some(ref completion) = nav.completion {
+            self.draw_completion(completion);
+        }
+        if let Some(msg) = prompt {
+            self.draw_prompt(msg);
+        }
+
         self.buffer.draw(&mut self.out)?;
         Ok(())
     }
 
+    // NOTE: the menu covers all panels
+    fn draw_menu(&mut self, menu: &Menu) {
+        let (cols, rows) = self.buffer.size;
+        let style = theme().ui_border;
+        for i in 0..cols {
+            for j in 1..rows - 1 {
+                self.buffer.set_content(i, j, ' ', &Style::default());
+            }
+            self.buffer.set_content(i, 1, '─', &style);
+            self.buffer.set_content(i, rows - 2, '─', &style);
+        }
+        for j in 2..rows - 2 {
+            self.buffer.set_content(0, j, '│', &style);
+            self.buffer.set_content(cols - 1, j, '│', &style);
+        }
+        self.buffer.set_content(0, 1, '┌', &style);
+        self.buffer.set_content(cols - 1, 1, '┐', &style);
+        self.buffer.set_content(0, rows - 2, '└', &style);
+        self.buffer.set_content(cols - 1, rows - 2, '┘', &style);
+
+        let title = format!(" {title} ", title = menu.title);
+        self.buffer
+            .set_line(2, 1, cols.saturating_sub(4), &title, &theme().dir);
+        let help = match menu.kind {
+            MenuKind::Jobs => " Enter:detail p:pause d:cancel Esc:close ",
+            MenuKind::Job(_) => " Enter:go to p:pause d:cancel h:jobs Esc:close ",
+            _ => " Enter:pick d:delete Esc:close ",
+        };
+        self.buffer
+            .set_line_from_right(cols - 2, rows - 2, help, &theme().file_info);
+
+        if menu.entries.is_empty() {
+            self.buffer
+                .set_line(1, 2, cols - 2, "empty", &theme().warn_msg);
+            return;
+        }
+        let height = (rows - 4) as usize;
+        let width = (cols - 2) as usize;
+        let sp = (menu.ind + 1).saturating_sub(height);
+        for (i, (_, text)) in menu.entries.iter().enumerate().skip(sp).take(height) {
+            let mut st = Style::default();
+            let mut line = format!(" {text}");
+            if i == menu.ind {
+                st.attrs.set(Attr::Reverse);
+                line.extend(std::iter::repeat_n(' ', width.saturating_sub(line.width())));
+            }
+            self.buffer
+                .set_line(1, 2 + (i - sp) as u16, cols - 2, &line, &st);
+        }
+    }
+
+    // NOTE: the prompt covers the status line
+    fn draw_prompt(&mut self, msg: &str) {
+        let (cols, rows) = self.buffer.size;
+        for i in 0..cols {
+            self.buffer.set_content(i, rows - 1, ' ', &Style::default());
+        }
+        self.buffer
+            .set_line(0, rows - 1, cols, msg, &theme().warn_msg);
+    }
+
+    // NOTE: candidates are drawn in columns right above the status line, and at most half of the
+    // screen is used, the rows around the selected candidate are shown.
+    fn draw_completion(&mut self, completion: &Completion) {
+        let (cols, rows) = self.buffer.size;
+        let cands = &completion.candidates;
+        let col_width = cands.iter().map(|c| c.width()).max().unwrap_or(0) + 2;
+        let per_row = std::cmp::max(cols as usize / col_width, 1);
+        let total_rows = cands.len().div_ceil(per_row);
+        let height = std::cmp::min(total_rows, (rows / 2) as usize);
+        let sp = completion
+            .ind
+            .map_or(0, |i| (i / per_row + 1).saturating_sub(height));
+        let top = rows - 1 - height as u16;
+        for j in top..rows - 1 {
+            for i in 0..cols {
+                self.buffer.set_content(i, j, ' ', &Style::default());
+            }
+        }
+        for (i, cand) in cands.iter().enumerate().skip(sp * per_row) {
+            let (row, col) = (i / per_row - sp, i % per_row);
+            if row >= height {
+                break;
+            }
+            let mut st = Style::default();
+            if completion.ind == Some(i) {
+                st.attrs.set(Attr::Reverse);
+            }
+            let x = (col * col_width) as u16;
+            self.buffer.set_line(
+                x,
+                top + row as u16,
+                cols.saturating_sub(x),
+                &format!(" {cand} "),
+                &st,
+            );
+        }
+    }
+
     fn draw_pwd(&mut self, path: &Path, file_name: &str) {
         let cols = self.buffer.size.0;
         let mut pos = 0;
         let user = format!("{user}:", user = self.user);
         self.buffer
-            .set_line(pos, 0, cols.saturating_sub(pos), &user, &theme::USER_STYLE);
+            .set_line(pos, 0, cols.saturating_sub(pos), &user, &theme().user);
         pos += user.width() as u16;
 
         let suffix = if path.ends_with("/") { "" } else { "/" };
@@ -147,26 +258,24 @@ impl Ui {
                 p.to_string()
             }
         } else {
-            log::error!("{path} may contain non-UTF-8 characters!!!", path = path.display());
+            log::error!(
+                "{path} may contain non-UTF-8 characters!!!",
+                path = path.display()
+            );
             String::from("")
         };
-        self.buffer.set_line(
-            pos,
-            0,
-            cols.saturating_sub(pos),
-            &path_str,
-            &theme::DIR_STYLE,
-        );
+        self.buffer
+            .set_line(pos, 0, cols.saturating_sub(pos), &path_str, &theme().dir);
         pos += path_str.width() as u16;
         self.buffer
-            .set_line(pos, 0, cols.saturating_sub(pos), suffix, &theme::DIR_STYLE);
+            .set_line(pos, 0, cols.saturating_sub(pos), suffix, &theme().dir);
         pos += suffix.width() as u16;
         self.buffer.set_line(
             pos,
             0,
             cols.saturating_sub(pos),
             file_name,
-            &theme::REG_FILE_STYLE,
+            &theme().reg_file,
         );
     }
 
@@ -178,17 +287,19 @@ impl Ui {
 
         let (cols, rows) = self.buffer.size;
         if !nav.error_message.is_empty() {
-            self.buffer.set_line(
-                0,
-                rows - 1,
-                cols,
-                &nav.error_message,
-                &theme::ERROR_MSG_STYLE,
-            );
+            self.buffer
+                .set_line(0, rows - 1, cols, &nav.error_message, &theme().error_msg);
             return;
         }
 
         let mut start = cols;
+        if nav.count != 0 {
+            let count = format!(" {count}", count = nav.count);
+            self.buffer
+                .set_line_from_right(start, rows - 1, &count, &theme().proportion);
+            start = start.saturating_sub(count.width() as u16);
+        }
+
         if dir.files_len != 0 {
             let proportion = format!(
                 " [{cur}/{all}]",
@@ -196,33 +307,30 @@ impl Ui {
                 all = dir.files_len
             );
             self.buffer
-                .set_line_from_right(start, rows - 1, &proportion, &theme::PROPORTION_STYLE);
+                .set_line_from_right(start, rows - 1, &proportion, &theme().proportion);
             start = start.saturating_sub(proportion.width() as u16);
         }
 
-        if nav.mv_cp_total_size != 0 {
-            let progress = format!(
-                " [{p:.0}%] ",
-                p = nav.mv_cp_size as f64 / nav.mv_cp_total_size as f64 * 100.0
-            );
+        if let Some(status) = nav.jobs.status() {
+            let progress = format!(" [{status}] ");
             self.buffer
-                .set_line_from_right(start, rows - 1, &progress, &theme::PROGRESS_STYLE);
+                .set_line_from_right(start, rows - 1, &progress, &theme().progress);
             start = start.saturating_sub(progress.width() as u16);
         }
 
         if !nav.selections.is_empty() {
             let sel = format!(" {count} ", count = nav.selections.len());
             self.buffer
-                .set_line_from_right(start, rows - 1, &sel, &theme::SELECTION_STYLE);
+                .set_line_from_right(start, rows - 1, &sel, &theme().selection);
             start = start.saturating_sub(sel.width() as u16);
         }
 
         if !nav.cut_or_copy.is_empty() {
             let cc = format!(" {count} ", count = nav.cut_or_copy.len());
             let style = if nav.is_cut {
-                &theme::CUT_STYLE
+                &theme().cut
             } else {
-                &theme::COPY_STYLE
+                &theme().copy
             };
             self.buffer.set_line_from_right(start, rows - 1, &cc, style);
             start = start.saturating_sub(cc.width() as u16);
@@ -236,17 +344,24 @@ impl Ui {
                 rows - 1,
                 start.saturating_sub(pos),
                 &info,
-                &theme::FILE_INFO_STYLE,
+                &theme().file_info,
             );
             pos += info.width() as u16;
             if file.file_type == FileType::Link {
                 let (dst, style) = match file.link_state.as_ref().unwrap() {
-                    LinkState::Working(dst) => (dst, &theme::LINK_WORKING_STYLE),
-                    LinkState::Broken(dst) => (dst, &theme::LINK_BROKEN_STYLE),
+                    LinkState::Working(dst) => (dst, theme().link_working),
+                    LinkState::Broken(dst) => (
+                        dst,
+                        theme()
+                            .ls_colors
+                            .as_ref()
+                            .and_then(|ls_colors| ls_colors.missing_style())
+                            .unwrap_or(theme().link_broken),
+                    ),
                 };
                 let dst = format!(" -> {dst}");
                 self.buffer
-                    .set_line(pos, rows - 1, start.saturating_sub(pos), &dst, style);
+                    .set_line(pos, rows - 1, start.saturating_sub(pos), &dst, &style);
                 // pos += dst.len() as u16;
             }
         }
@@ -262,6 +377,16 @@ impl Ui {
             prefix = nav.cmd_prefix,
             cmd = nav.cmd_string
         );
+        if let Some(ref query) = nav.history_search {
+            let search = format!("(reverse-i-search)`{query}'");
+            let line = format!("{search}: {cmd}");
+            self.buffer
+                .set_line(0, rows - 1, cols, &line, &Style::default());
+            let cursor_pos = search.width() - 1;
+            queue!(self.out, cursor::MoveTo(cursor_pos as u16, rows - 1))
+                .unwrap_or_else(|err| log::error!("{err} => crossterm::queue!() failed"));
+            return;
+        }
         self.buffer
             .set_line(0, rows - 1, cols, &cmd, &Style::default());
         let cmd_cursor_pos = nav.cmd_string[..nav.cmd_string_ind].width() + 1;
@@ -353,9 +478,9 @@ impl Ui {
         }
 
         let cc_style = if nav.is_cut {
-            &theme::CUT_STYLE
+            &theme().cut
         } else {
-            &theme::COPY_STYLE
+            &theme().copy
         };
 
         if let Some(files) = dir.files() {
@@ -378,7 +503,7 @@ impl Ui {
                         self.wins[win_id] + 1,
                         2 + ind as u16,
                         ' ',
-                        &theme::SELECTION_STYLE,
+                        &theme().selection,
                     );
                 }
                 if nav.cut_or_copy.contains(&file.file_path) {
@@ -404,29 +529,7 @@ impl Ui {
     }
 
     fn gen_styled_filename(&self, file: &File, cols: u16, sel: bool) -> (String, Style) {
-        use crate::config::theme::*;
-        let mut style = match file.file_type {
-            FileType::RegularFile => {
-                if file.is_executable() {
-                    EXECUTABLE_FILE_STYLE
-                } else {
-                    REG_FILE_STYLE
-                }
-            }
-            FileType::Directory => DIR_STYLE,
-            FileType::Link => match file.link_state {
-                Some(ref state) => match state {
-                    LinkState::Working(_) => LINK_WORKING_STYLE,
-                    LinkState::Broken(_) => LINK_BROKEN_STYLE,
-                },
-                None => REG_FILE_STYLE,
-            },
-            FileType::Pipe => PIPE_FILE_STYLE,
-            FileType::Socket => SOCKET_FILE_STYLE,
-            FileType::CharDevice => CHAR_FILE_STYLE,
-            FileType::BlockDevice => BLOCK_FILE_STYLE,
-            _ => REG_FILE_STYLE,
-        };
+        let mut style = theme().file_style(file);
 
         if sel {
             style.attrs.set(Attr::Reverse);
@@ -451,13 +554,13 @@ impl Ui {
     fn draw_warn_message(&mut self, win_id: usize, msg: &str) {
         let cols = self.wins[win_id + 1] - self.wins[win_id] - 1;
         self.buffer
-            .set_line(self.wins[win_id] + 1, 2, cols, msg, &theme::WARN_MSG_STYLE);
+            .set_line(self.wins[win_id] + 1, 2, cols, msg, &theme().warn_msg);
     }
 
     fn draw_error_message(&mut self, win_id: usize, msg: &str) {
         let cols = self.wins[win_id + 1] - self.wins[win_id] - 1;
         self.buffer
-            .set_line(self.wins[win_id] + 1, 2, cols, msg, &theme::ERROR_MSG_STYLE);
+            .set_line(self.wins[win_id] + 1, 2, cols, msg, &theme().error_msg);
     }
 
     pub fn suspend(&mut self) {
@@ -2,15 +2,19 @@ use crossbeam_channel::{unbounded, Receiver, Sender};
 use crossterm::event::KeyCode;
 use crossterm::{event::Event, terminal};
 use std::{
-    fs,
-    io::{Read, Write},
-    path::Path,
+    env, fs,
+    io::{ErrorKind, IsTerminal, Read, Write},
+    os::unix::fs::{OpenOptionsExt, PermissionsExt},
+    path::{Component, Path, PathBuf},
+    time::Duration,
 };
 use unicode_width::UnicodeWidthChar;
 use walkdir::{DirEntry, WalkDir};
 
-use crate::config::WORD_SEPS;
+use crate::app::Answer;
 use crate::file::File;
+use crate::job;
+use crate::settings::settings;
 
 macro_rules! nth {
     ($val:expr, $index:expr) => {
@@ -50,11 +54,26 @@ pub struct CrossTermEventChan {
 }
 impl_chan!(CrossTermEventChan, ev_send, ev_recv, Event);
 
-pub struct U64Chan {
-    pub u64_recv: Receiver<u64>,
-    pub u64_send: Sender<u64>,
+// NOTE: events of `paste` and `remove` threads, tagged with the job id
+pub struct JobChan {
+    pub job_recv: Receiver<(usize, job::Event)>,
+    pub job_send: Sender<(usize, job::Event)>,
 }
-impl_chan!(U64Chan, u64_send, u64_recv, u64);
+impl_chan!(JobChan, job_send, job_recv, (usize, job::Event));
+
+// NOTE: a question and where to send the answer
+pub struct AskChan {
+    pub ask_recv: Receiver<(String, Sender<Answer>)>,
+    pub ask_send: Sender<(String, Sender<Answer>)>,
+}
+impl_chan!(AskChan, ask_send, ask_recv, (String, Sender<Answer>));
+
+// NOTE: the id of a prompt and its new message
+pub struct PromptChan {
+    pub prompt_recv: Receiver<(usize, String)>,
+    pub prompt_send: Sender<(usize, String)>,
+}
+impl_chan!(PromptChan, prompt_send, prompt_recv, (usize, String));
 
 pub struct StringChan {
     pub string_recv: Receiver<String>,
@@ -123,8 +142,31 @@ pub fn terminal_size() -> (u16, u16) {
         .unwrap()
 }
 
+// NOTE: when stdout is redirected(like `cd "$(tfm --print-last-dir)"`), the terminal is
+// `/dev/tty`. None if stdout is the terminal.
+pub fn tty() -> Option<fs::File> {
+    if std::io::stdout().is_terminal() {
+        return None;
+    }
+    match fs::OpenOptions::new().write(true).open("/dev/tty") {
+        Ok(tty) => Some(tty),
+        Err(err) => {
+            log::error!("{err} => open `/dev/tty` failed, using stdout instead");
+            None
+        }
+    }
+}
+
+// NOTE: the UI, shell commands and their prompts are written to it, never to a redirected stdout.
+pub fn tty_out() -> Box<dyn Write> {
+    match tty() {
+        Some(tty) => Box::new(tty),
+        None => Box::new(std::io::stdout()),
+    }
+}
+
 pub fn pause() {
-    let mut stdout = std::io::stdout();
+    let mut stdout = tty_out();
     stdout
         .write_all(b"Press Enter to continue...")
         .map_err(|err| {
@@ -195,14 +237,16 @@ pub fn keycode2str(code: KeyCode) -> String {
     ret
 }
 
-pub fn dir_size(p: &Path) -> Result<u64, String> {
-    let mut ret = 0u64;
+// The count of files(directories and symlinks included) under `p` and their total size
+pub fn dir_summary(p: &Path) -> Result<(usize, u64), String> {
+    let (mut count, mut ret) = (0usize, 0u64);
     if p.is_symlink() {
-        return Ok(0);
+        return Ok((1, 0));
     }
     for entry in WalkDir::new(p) {
         match entry {
             Ok(entry) => {
+                count += 1;
                 if !entry.path_is_symlink() {
                     match entry.metadata() {
                         Ok(metadata) => {
@@ -241,10 +285,54 @@ pub fn dir_size(p: &Path) -> Result<u64, String> {
             }
         }
     }
-    Ok(ret)
+    Ok((count, ret))
+}
+
+const COPY_CHUNK: usize = 1 << 20;
+
+// NOTE: like `fs::copy`, but `progress` is called with the size of every copied chunk. The chunked
+// copy loses the fast paths of `fs::copy`(`copy_file_range`, reflinks on btrfs/XFS), so files of
+// one chunk or less are still copied by `fs::copy`.
+pub fn copy_file(from: &Path, to: &Path, progress: &mut dyn FnMut(u64)) -> std::io::Result<u64> {
+    let mut reader = fs::File::open(from)?;
+    let metadata = reader.metadata()?;
+    if metadata.len() <= COPY_CHUNK as u64 {
+        let size = fs::copy(from, to)?;
+        progress(size);
+        return Ok(size);
+    }
+    let permissions = metadata.permissions();
+    // NOTE: created with the source's mode, so it's never more accessible than the source.
+    // An existing file keeps its mode until the copy is done.
+    let mut writer = fs::OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .mode(permissions.mode())
+        .open(to)?;
+    let mut buf = vec![0; COPY_CHUNK];
+    let mut size = 0;
+    loop {
+        let n = match reader.read(&mut buf) {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(err) if err.kind() == ErrorKind::Interrupted => continue,
+            Err(err) => return Err(err),
+        };
+        writer.write_all(&buf[..n])?;
+        size += n as u64;
+        progress(n as u64);
+    }
+    writer.set_permissions(permissions)?;
+    Ok(size)
 }
 
-pub fn dir_copy(entry: &DirEntry, dst_dir: &Path, base_dir: &Path) -> std::io::Result<u64> {
+pub fn dir_copy(
+    entry: &DirEntry,
+    dst_dir: &Path,
+    base_dir: &Path,
+    progress: &mut dyn FnMut(u64),
+) -> std::io::Result<u64> {
     let from = entry.path();
     let suffix_path = format!("{}", from.strip_prefix(base_dir).unwrap().display());
     let to = if suffix_path.is_empty() {
@@ -268,14 +356,51 @@ pub fn dir_copy(entry: &DirEntry, dst_dir: &Path, base_dir: &Path) -> std::io::R
     } else if from.is_dir() {
         fs::create_dir_all(to)?;
     } else {
-        return fs::copy(from, to);
+        return copy_file(from, &to, progress);
     }
     Ok(0)
 }
 
+// Copy `from` to `to` recursively, symbolic links are copied as links.
+pub fn copy_path(from: &Path, to: &Path) -> std::io::Result<()> {
+    if from.is_symlink() {
+        return std::os::unix::fs::symlink(fs::read_link(from)?, to);
+    }
+    for entry in WalkDir::new(from) {
+        dir_copy(&entry?, to, from, &mut |_| {})?;
+    }
+    Ok(())
+}
+
+// NOTE: fall back to copying and removing if `from` and `to` are on different filesystems
+pub fn move_path(from: &Path, to: &Path) -> std::io::Result<()> {
+    match fs::rename(from, to) {
+        Err(err) if err.kind() == ErrorKind::CrossesDevices => {
+            copy_path(from, to)?;
+            dir_remove(from)
+        }
+        res => res,
+    }
+}
+
+// Replace `dst` with `from` in the same directory, `dst` is kept if it fails.
+// NOTE: `dst` is renamed aside first, since a directory can't be renamed over a non-empty one.
+pub fn replace_path(from: &Path, dst: &Path) -> std::io::Result<()> {
+    let name = dst.file_name().unwrap_or_default().to_string_lossy();
+    let old = dst.with_file_name(format!(".{name}.tfm-old"));
+    dir_remove(&old)?;
+    fs::rename(dst, &old)?;
+    if let Err(err) = fs::rename(from, dst) {
+        let _ = fs::rename(&old, dst);
+        return Err(err);
+    }
+    dir_remove(&old)
+}
+
+// NOTE: symbolic links are removed themselves, even if they are broken.
 pub fn dir_remove(p: &Path) -> std::io::Result<()> {
-    if p.exists() {
-        if p.is_dir() {
+    if let Ok(metadata) = p.symlink_metadata() {
+        if metadata.is_dir() {
             fs::remove_dir_all(p)?
         } else {
             fs::remove_file(p)?
@@ -284,11 +409,111 @@ pub fn dir_remove(p: &Path) -> std::io::Result<()> {
     Ok(())
 }
 
+// Number of files(directories included) and their total size under `paths`, errors are ignored.
+pub fn files_summary(paths: &[PathBuf]) -> (usize, u64) {
+    let (mut count, mut size) = (0, 0);
+    for p in paths {
+        for entry in WalkDir::new(p).into_iter().flatten() {
+            count += 1;
+            if !entry.path_is_symlink() {
+                size += entry.metadata().map_or(0, |m| m.len());
+            }
+        }
+    }
+    (count, size)
+}
+
+// Like `1.5K` or `300.0B`
+pub fn human_size(size: u64) -> String {
+    let unit = &['B', 'K', 'M', 'G'];
+    let mut s = size;
+    let mut divisor = 1f64;
+    let mut i = 0usize;
+    while i < unit.len() - 1 {
+        if s < 1024 {
+            break;
+        }
+        s >>= 10;
+        divisor *= 1024f64;
+        i += 1;
+    }
+    format!(
+        "{size:.1}{unit}",
+        size = size as f64 / divisor,
+        unit = unit[i]
+    )
+}
+
+// Like `1:05` or `2:01:05`
+pub fn human_duration(d: Duration) -> String {
+    let secs = d.as_secs();
+    match secs / 3600 {
+        0 => format!("{m}:{s:02}", m = secs / 60, s = secs % 60),
+        h => format!("{h}:{m:02}:{s:02}", m = secs / 60 % 60, s = secs % 60),
+    }
+}
+
+// NOTE: symbolic links aren't resolved unlike `canonicalize`, and `..` is removed lexically like
+// `cd` of shells.
+pub fn absolute(p: &Path) -> PathBuf {
+    let p = std::path::absolute(p).unwrap_or_else(|_| p.to_path_buf());
+    let mut ret = PathBuf::new();
+    for c in p.components() {
+        match c {
+            Component::ParentDir => {
+                ret.pop();
+            }
+            Component::CurDir => {}
+            c => ret.push(c),
+        }
+    }
+    ret
+}
+
+// NOTE: only `~` and `~/...` are expanded, `~user` is kept as it is.
+pub fn expand_tilde(s: &str) -> PathBuf {
+    let home = env::var("HOME").unwrap_or_default();
+    if s == "~" {
+        PathBuf::from(home)
+    } else if let Some(rest) = s.strip_prefix("~/") {
+        PathBuf::from(home).join(rest)
+    } else {
+        PathBuf::from(s)
+    }
+}
+
+// `$XDG_CONFIG_HOME/tfm` or `~/.config/tfm`
+pub fn config_dir() -> PathBuf {
+    match env::var("XDG_CONFIG_HOME") {
+        Ok(dir) if !dir.is_empty() => PathBuf::from(dir).join("tfm"),
+        _ => expand_tilde("~/.config/tfm"),
+    }
+}
+
+// `$XDG_DATA_HOME/tfm` or `~/.local/share/tfm`
+// NOTE: write to a temporary file and rename it, so that other instances never read a half-written
+// file. The parent directories are created if needed.
+pub fn write_atomic(path: &Path, content: &str) -> std::io::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let tmp = path.with_extension(format!("{}", std::process::id()));
+    fs::File::create(&tmp)?.write_all(content.as_bytes())?;
+    fs::rename(&tmp, path)
+}
+
+pub fn data_dir() -> PathBuf {
+    match env::var("XDG_DATA_HOME") {
+        Ok(dir) if !dir.is_empty() => PathBuf::from(dir).join("tfm"),
+        _ => expand_tilde("~/.local/share/tfm"),
+    }
+}
+
 pub fn find_word(s: &str, ind: usize) -> (usize, usize) {
     let (old, mut new) = (ind, ind);
     if !s.is_empty() && new <= s.len() && new > 0 {
         let mut ch = s[..new].chars().last().unwrap();
-        while new > 0 && (ch.is_ascii_whitespace() || WORD_SEPS.contains(&ch)) {
+        while new > 0 && (ch.is_ascii_whitespace() || settings().word_seps.contains(&ch)) {
             new -= ch.len_utf8();
             ch = if let Some(ch) = s[..new].chars().last() {
                 ch
@@ -296,7 +521,7 @@ pub fn find_word(s: &str, ind: usize) -> (usize, usize) {
                 break;
             }
         }
-        while new > 0 && !ch.is_ascii_whitespace() && !WORD_SEPS.contains(&ch) {
+        while new > 0 && !ch.is_ascii_whitespace() && !settings().word_seps.contains(&ch) {
             new -= ch.len_utf8();
             ch = if let Some(ch) = s[..new].chars().last() {
                 ch
@@ -312,7 +537,7 @@ pub fn find_word_rev(s: &str, ind: usize) -> (usize, usize) {
     let (old, mut new) = (ind, ind);
     if !s.is_empty() && new < s.len() {
         let mut ch = s[new..].chars().next().unwrap();
-        while new < s.len() && (ch.is_ascii_whitespace() || WORD_SEPS.contains(&ch)) {
+        while new < s.len() && (ch.is_ascii_whitespace() || settings().word_seps.contains(&ch)) {
             new += ch.len_utf8();
             ch = if let Some(ch) = s[new..].chars().next() {
                 ch
@@ -320,7 +545,7 @@ pub fn find_word_rev(s: &str, ind: usize) -> (usize, usize) {
                 break;
             }
         }
-        while new < s.len() && !ch.is_ascii_whitespace() && !WORD_SEPS.contains(&ch) {
+        while new < s.len() && !ch.is_ascii_whitespace() && !settings().word_seps.contains(&ch) {
             new += ch.len_utf8();
             ch = if let Some(ch) = s[new..].chars().next() {
                 ch
@@ -332,6 +557,50 @@ pub fn find_word_rev(s: &str, ind: usize) -> (usize, usize) {
     (old, new)
 }
 
+// A temporary directory for tests, with a unique name. It's removed when dropped, even if the test
+// panics.
+#[cfg(test)]
+pub struct TempDir(PathBuf);
+
+#[cfg(test)]
+impl TempDir {
+    pub fn new(name: &str) -> Self {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        static COUNT: AtomicUsize = AtomicUsize::new(0);
+        let dir = env::temp_dir().join(format!(
+            "tfm-{name}-{pid}-{n}",
+            pid = std::process::id(),
+            n = COUNT.fetch_add(1, Ordering::Relaxed)
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        Self(dir)
+    }
+}
+
+#[cfg(test)]
+impl std::ops::Deref for TempDir {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+impl AsRef<Path> for TempDir {
+    fn as_ref(&self) -> &Path {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -371,4 +640,72 @@ mod test {
         );
         assert_eq!("bcd", shrink_unicode_str_rev("站着说话不腰疼🤣abcd", 3));
     }
+
+    #[test]
+    fn test_human_size() {
+        assert_eq!("300.0B", human_size(300));
+        assert_eq!("1.5K", human_size(1536));
+        assert_eq!("2.0G", human_size(2 << 30));
+        assert_eq!("2048.0G", human_size(2 << 40));
+    }
+
+    #[test]
+    fn test_absolute() {
+        assert_eq!(PathBuf::from("/a/c"), absolute(Path::new("/a/b/../c/.")));
+        assert_eq!(PathBuf::from("/"), absolute(Path::new("/a/../..")));
+    }
+
+    #[test]
+    fn test_human_duration() {
+        assert_eq!("0:05", human_duration(Duration::from_secs(5)));
+        assert_eq!("1:05", human_duration(Duration::from_millis(65_500)));
+        assert_eq!("2:01:05", human_duration(Duration::from_secs(7265)));
+    }
+
+    #[test]
+    fn test_copy_file() {
+        let dir = TempDir::new("copy");
+        let (from, to) = (dir.join("from"), dir.join("to"));
+        let content = vec![7u8; COPY_CHUNK * 2 + 10];
+        fs::write(&from, &content).unwrap();
+        fs::set_permissions(&from, fs::Permissions::from_mode(0o640)).unwrap();
+        let mut chunks = Vec::new();
+        let size = copy_file(&from, &to, &mut |n| chunks.push(n)).unwrap();
+        assert_eq!(content.len() as u64, size);
+        assert_eq!(size, chunks.iter().sum::<u64>());
+        assert!(chunks.len() >= 3);
+        assert_eq!(content, fs::read(&to).unwrap());
+        assert_eq!(
+            0o640,
+            fs::metadata(&to).unwrap().permissions().mode() & 0o777
+        );
+
+        // NOTE: a small file is copied at once
+        let (from, to) = (dir.join("small"), dir.join("small-copy"));
+        fs::write(&from, "small").unwrap();
+        fs::set_permissions(&from, fs::Permissions::from_mode(0o600)).unwrap();
+        let mut chunks = Vec::new();
+        assert_eq!(5, copy_file(&from, &to, &mut |n| chunks.push(n)).unwrap());
+        assert_eq!(vec![5], chunks);
+        assert_eq!(
+            0o600,
+            fs::metadata(&to).unwrap().permissions().mode() & 0o777
+        );
+    }
+
+    #[test]
+    fn test_replace_path() {
+        let dir = TempDir::new("replace");
+        fs::create_dir_all(dir.join("dst")).unwrap();
+        fs::write(dir.join("dst/a"), "old").unwrap();
+        fs::write(dir.join("from"), "new").unwrap();
+        replace_path(&dir.join("from"), &dir.join("dst")).unwrap();
+        assert_eq!("new", fs::read_to_string(dir.join("dst")).unwrap());
+        assert!(!dir.join("from").exists());
+        assert!(!dir.join(".dst.tfm-old").exists());
+
+        // NOTE: `dst` is kept if `from` doesn't exist
+        assert!(replace_path(&dir.join("from"), &dir.join("dst")).is_err());
+        assert_eq!("new", fs::read_to_string(dir.join("dst")).unwrap());
+    }
 }